@@ -1,4 +1,4 @@
-import init, { Cell, Color4, Universe } from './pkg/game_of_life.js'
+import init, { Backend, Cell, Color4, Connectivity, CycleStatus, Neighborhood, Topology, Universe } from './pkg/game_of_life.js'
 
 
 /// Initialize UI associated with this app. Returns:
@@ -6,6 +6,7 @@ import init, { Cell, Color4, Universe } from './pkg/game_of_life.js'
 ///   canvas: A canvas.mainDisplay,
 ///   controls: {
 ///     addButton: (title, clickAction),
+///     addSelect: (label, options, onChange),
 ///     elem: HTMLElement
 ///   }
 /// }
@@ -56,6 +57,27 @@ function initUI(parent) {
 
                 return input;
             },
+            addSelect: (label, options, onChange) => {
+                const labelElem = document.createElement("label");
+                labelElem.textContent = label;
+
+                const select = document.createElement("select");
+                for (const [text, value] of options) {
+                    const option = document.createElement("option");
+                    option.textContent = text;
+                    option.value = value;
+                    select.appendChild(option);
+                }
+
+                labelElem.appendChild(select);
+                controls.appendChild(labelElem);
+
+                select.addEventListener("change", (evt) => {
+                    onChange(select.value, evt);
+                });
+
+                return select;
+            },
             element: controls,
         },
     };
@@ -63,7 +85,7 @@ function initUI(parent) {
 
 async function run() {
     let running = true;
-    let updateBtnText, updateSquareSize, mainloop, togglePaused, clearUniverse;
+    let updateBtnText, updateSquareSize, mainloop, togglePaused, clearUniverse, undo, redo;
     let playPauseButton, fillRectCellsCB;
     let render;
     let updateRate = 1;
@@ -73,12 +95,74 @@ async function run() {
     const CELL_COLOR = Color4.new(0, 0, 0, 255);
 
     let universe = Universe.new(64, 64);
+    if (location.hash.length > 1) {
+        try {
+            const shared = Universe.from_url_code(location.hash.substring(1));
+            universe.free();
+            universe = shared;
+        } catch (error) {
+            console.error(error);
+        }
+    }
+    universe.set_history_depth(1000);
+    universe.set_cycle_window(256);
     let uiData = initUI(document.body);
     const canvas = uiData.canvas;
     const controls = uiData.controls;
 
     playPauseButton = controls.addButton("Pause", () => togglePaused());
     controls.addButton("Clear", () => clearUniverse());
+    controls.addButton("Undo", () => undo());
+    controls.addButton("Redo", () => redo());
+    controls.addButton("Step back", () => {
+        if (running) {
+            togglePaused();
+        }
+
+        universe.step_back(1);
+        render();
+    });
+
+    controls.addButton("Share", () => {
+        location.hash = universe.to_url_code();
+    });
+    controls.addButton("Save", () => {
+        const snapshot = universe.to_snapshot();
+        localStorage.setItem("snapshot", btoa(String.fromCharCode(...snapshot)));
+    });
+    controls.addButton("Restore", () => {
+        const saved = localStorage.getItem("snapshot");
+        if (saved === null) {
+            return;
+        }
+
+        try {
+            const snapshot = Uint8Array.from(atob(saved), c => c.charCodeAt(0));
+            const restored = Universe.from_snapshot(snapshot);
+            universe.free();
+            universe = restored;
+            universe.set_history_depth(1000);
+            universe.set_cycle_window(256);
+        } catch (error) {
+            console.error(error);
+        }
+
+        render();
+    });
+
+    controls.addInput("Soup seed: ", "number", (value) => {
+        if (Math.floor(value) == value && value >= 0 && !isNaN(value)) {
+            const size = Math.min(16, universe.width(), universe.height());
+            const x = Math.floor((universe.width() - size) / 2);
+            const y = Math.floor((universe.height() - size) / 2);
+
+            universe.clear();
+            universe.randomize_region(x, y, size, size, 0.5, BigInt(value));
+            render();
+        }
+    }, (input) => {
+        input.value = 0;
+    });
 
     controls.addInput("Width: ", "number", (value) => {
         if (Math.floor(value) == value && value > 0 && !isNaN(value)) {
@@ -110,18 +194,81 @@ async function run() {
         input.max = 12;
     });
 
+    controls.addInput("Rule: ", "text", (value, evt) => {
+        try {
+            universe.set_rule(value);
+            evt.target.setCustomValidity("");
+
+            // Hexagonal rules are drawn in a wider layout.
+            updateSquareSize();
+            render();
+        } catch (error) {
+            evt.target.setCustomValidity(error);
+        }
+
+        evt.target.reportValidity();
+    }, (input) => {
+        input.value = universe.rule();
+    });
+
+    controls.addSelect("Edges: ", [
+        ["Torus", Topology.Torus],
+        ["Plane", Topology.Plane],
+        ["Cylinder", Topology.Cylinder],
+        ["Klein bottle", Topology.KleinBottle],
+        ["Cross-surface", Topology.CrossSurface],
+    ], (value) => {
+        universe.set_topology(parseInt(value));
+    });
+
+    controls.addInput("Bit-packed: ", "checkbox", (_, evt) => {
+        universe.set_backend(evt.target.checked ? Backend.BitPacked : Backend.Bytes);
+    }, (input) => {
+        input.checked = universe.backend() == Backend.BitPacked;
+    });
+
     fillRectCellsCB = controls.addInput("Use fill_rect: ", "checkbox", (_) => { render(); },
         (input) => {
             input.checked = false;
         }
     );
 
+    const statsDisplay = document.createElement("span");
+    controls.element.appendChild(statsDisplay);
+
+    const censusDisplay = document.createElement("pre");
+    controls.addButton("Census", () => {
+        try {
+            const census = universe.census(Connectivity.Moore);
+            const lines = [];
+
+            for (let i = 0; i < census.len(); i++) {
+                const name = census.name(i);
+                lines.push(`${census.count(i)}\t${census.code(i)}${name ? ` (${name})` : ""}`);
+            }
+
+            censusDisplay.textContent = lines.join("\n");
+        } catch (error) {
+            censusDisplay.textContent = error;
+        }
+    });
+    controls.element.appendChild(censusDisplay);
+
     updateBtnText = () => {
         playPauseButton.textContent = running ? "Pause" : "Play";
     };
 
     updateSquareSize = () => {
-        const full_cell_size = Math.min(canvas.width, canvas.height)/Math.max(universe.width(), universe.height());
+        let full_cell_size = Math.min(canvas.width, canvas.height)/Math.max(universe.width(), universe.height());
+
+        // Each row of hexagons is offset by half a cell, and rows are sqrt(3)/2 cells apart.
+        if (universe.neighborhood() == Neighborhood.Hexagonal) {
+            const layoutWidth = universe.width() + (universe.height() - 1) / 2;
+            const layoutHeight = (universe.height() - 1) * Math.sqrt(3) / 2 + 2 / Math.sqrt(3);
+
+            full_cell_size = Math.min(canvas.width / layoutWidth, canvas.height / layoutHeight);
+        }
+
         universe.set_square_spacing(full_cell_size > 2.0 ? 1.0 : 0.0);
 
         const square_size = full_cell_size - universe.get_square_spacing();
@@ -139,12 +286,38 @@ async function run() {
 
         ctx.clearRect(0, 0, canvas.width, canvas.height);
 
-        if (fillRectCellsCB.checked) {
+        if (universe.neighborhood() == Neighborhood.Hexagonal) {
+            ctx.fillStyle = "black";
+            universe.fill_hex_cells(Cell.Alive, ctx);
+
+            for (let state = 2; state < universe.states(); state++) {
+                ctx.fillStyle = `rgba(0, 0, 0, ${(state - 1) / (universe.states() - 1)})`;
+                universe.fill_hex_state(state, ctx);
+            }
+        } else if (fillRectCellsCB.checked) {
             ctx.fillStyle = "black";
             universe.fill_cells(Cell.Dead, ctx);
+
+            // Dying cells of Generations rules darken as they die.
+            for (let state = 2; state < universe.states(); state++) {
+                ctx.fillStyle = `rgba(0, 0, 0, ${(state - 1) / (universe.states() - 1)})`;
+                universe.fill_state(state, ctx);
+            }
         } else {
             universe.render_cells(Cell.Alive, CELL_COLOR, ctx);
         }
+
+        statsDisplay.textContent = `Generation ${universe.generation()}, population ${universe.population()}`
+            + ` (+${universe.births()} -${universe.deaths()})`;
+
+        if (universe.cycle_status() == CycleStatus.Stable) {
+            statsDisplay.textContent += `, stable since generation ${universe.cycle_start()}`;
+        } else if (universe.cycle_status() == CycleStatus.Oscillating) {
+            statsDisplay.textContent += `, period ${universe.cycle_period()} since generation ${universe.cycle_start()}`;
+        } else if (universe.cycle_status() == CycleStatus.Spaceship) {
+            statsDisplay.textContent += `, ${universe.cycle_speed()} spaceship moving (${universe.cycle_dx()}, ${universe.cycle_dy()})`
+                + ` every ${universe.cycle_period()} generations`;
+        }
     };
 
     clearUniverse = () => {
@@ -152,6 +325,18 @@ async function run() {
         render();
     };
 
+    undo = () => {
+        if (universe.undo()) {
+            render();
+        }
+    };
+
+    redo = () => {
+        if (universe.redo()) {
+            render();
+        }
+    };
+
     togglePaused = () => {
         running = !running;
         updateBtnText();
@@ -168,12 +353,18 @@ async function run() {
         const bbox = canvas.getBoundingClientRect();
         const squareSize = universe.get_square_size() + universe.get_square_spacing();
 
-        const x = Math.floor((evt.clientX - bbox.left) / squareSize);
-        const y = Math.floor((evt.clientY - bbox.top) / squareSize);
+        let x = Math.floor((evt.clientX - bbox.left) / squareSize);
+        let y = Math.floor((evt.clientY - bbox.top) / squareSize);
+
+        // Undo the offset and row spacing of the hexagonal layout (see fill_hex_state).
+        if (universe.neighborhood() == Neighborhood.Hexagonal) {
+            y = Math.floor((evt.clientY - bbox.top) / (squareSize * Math.sqrt(3) / 2));
+            x = Math.floor((evt.clientX - bbox.left) / squareSize - (universe.height() - 1 - y) / 2);
+        }
 
         if (x == lastCellX && y == lastCellY) {
             return;
-        } else if (x >= universe.width() || y >= universe.height()) {
+        } else if (x < 0 || y < 0 || x >= universe.width() || y >= universe.height()) {
             return;
         }
 
@@ -196,8 +387,15 @@ async function run() {
             lastCellX = undefined;
             lastCellY = undefined;
 
+            // Each stroke is undone as a whole.
+            universe.begin_transaction();
+
             return true;
         } else {
+            if (ptrDown) {
+                universe.end_transaction();
+            }
+
             ptrDown = false;
 
             return false;
@@ -208,11 +406,16 @@ async function run() {
             evt.preventDefault();
             ptrDown = false;
             handlePtrEvent(evt);
+            universe.end_transaction();
 
             return true;
         }
     }, false);
     canvas.addEventListener("pointerleave", (evt) => {
+        if (ptrDown) {
+            universe.end_transaction();
+        }
+
         ptrDown = false;
     });
     canvas.addEventListener("pointermove", (evt) => {
@@ -227,6 +430,10 @@ async function run() {
     document.body.addEventListener("keydown", evt => {
         if (evt.key == "p") {
             togglePaused();
+        } else if (evt.ctrlKey && (evt.key == "y" || evt.key == "Z")) {
+            redo();
+        } else if (evt.ctrlKey && evt.key == "z") {
+            undo();
         }
     });
 
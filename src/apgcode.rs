@@ -0,0 +1,347 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Pattern, Rule, Universe};
+
+/// Coordinates of a cell on an unbounded plane.
+pub(crate) type Point = (i64, i64);
+
+/// Patterns are run for at most this many generations to find their period.
+const MAX_GENERATIONS: usize = 1024;
+
+/// Patterns that grow beyond this many cells aren't run any further.
+const MAX_POPULATION: usize = 4096;
+
+/// Characters for the values of 5-cell column slices (0 to 31), and for
+/// the lengths of runs of empty columns after a 'y' (4 to 39).
+const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Height of the strips a pattern is split into.
+const STRIP_HEIGHT: i64 = 5;
+
+/// Translates [cells] so that their bounding box's top-left corner is at
+/// (0, 0), and sorts them.
+pub(crate) fn normalize(cells: &[Point]) -> Vec<Point> {
+    let left = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let top = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+
+    let mut normalized: Vec<Point> = cells.iter().map(|&(x, y)| (x - left, y - top)).collect();
+    normalized.sort_unstable_by_key(|&(x, y)| (y, x));
+    normalized
+}
+
+/// The 8 rotations and reflections of the plane.
+const ORIENTATIONS: [fn(i64, i64) -> Point; 8] = [
+    |x, y| (x, y), |x, y| (-x, y), |x, y| (x, -y), |x, y| (-x, -y),
+    |x, y| (y, x), |x, y| (-y, x), |x, y| (y, -x), |x, y| (-y, -x),
+];
+
+/// Returns [cells] under each of ORIENTATIONS, normalized.
+fn orientations(cells: &[Point]) -> impl Iterator<Item = Vec<Point>> + '_ {
+    ORIENTATIONS.iter().map(move |transform| {
+        let transformed: Vec<Point> = cells.iter().map(|&(x, y)| transform(x, y)).collect();
+        normalize(&transformed)
+    })
+}
+
+/// Encodes the live [cells] in extended Wechsler format (the part of an
+/// apgcode such as "xs4_33" after the underscore), relative to the top-left
+/// corner of their bounding box.
+pub(crate) fn encode(cells: &[Point]) -> String {
+    if cells.is_empty() {
+        return "0".to_string();
+    }
+
+    let cells = normalize(cells);
+    let width = cells.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+    let height = cells.iter().map(|&(_, y)| y + 1).max().unwrap_or(0);
+
+    let mut strips = vec![vec![0_usize; width as usize]; ((height + STRIP_HEIGHT - 1) / STRIP_HEIGHT) as usize];
+    for &(x, y) in &cells {
+        strips[(y / STRIP_HEIGHT) as usize][x as usize] |= 1 << (y % STRIP_HEIGHT);
+    }
+
+    let encoded: Vec<String> = strips.iter().map(|columns| encode_strip(columns)).collect();
+    encoded.join("z")
+}
+
+/// Encodes the columns of a strip, abbreviating runs of empty columns and
+/// omitting trailing ones.
+fn encode_strip(columns: &[usize]) -> String {
+    let used = columns.iter().rposition(|&column| column != 0).map_or(0, |last| last + 1);
+    let mut encoded = String::new();
+
+    let mut empty_run = 0;
+    for &column in &columns[..used] {
+        if column == 0 {
+            empty_run += 1;
+            continue;
+        }
+
+        encode_empty_run(empty_run, &mut encoded);
+        empty_run = 0;
+
+        encoded.push(DIGITS[column] as char);
+    }
+
+    encoded
+}
+
+fn encode_empty_run(mut length: usize, encoded: &mut String) {
+    while length > 0 {
+        let abbreviated = match length {
+            1 => "0".to_string(),
+            2 => "w".to_string(),
+            3 => "x".to_string(),
+            _ => format!("y{}", DIGITS[length.min(39) - 4] as char),
+        };
+
+        encoded.push_str(&abbreviated);
+        length -= length.min(39);
+    }
+}
+
+/// Returns the canonical encoding of a pattern with the given [phases]: the
+/// shortest encoding of any phase under any rotation or reflection, with ties
+/// broken by comparing the encodings as strings.
+pub(crate) fn canonical_encoding(phases: &[Vec<Point>]) -> String {
+    phases.iter()
+        .flat_map(|phase| orientations(phase))
+        .map(|cells| encode(&cells))
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_default()
+}
+
+/// Decodes an extended Wechsler [encoding] into the live cells it describes.
+fn decode(encoding: &str) -> Result<Vec<Point>, String> {
+    let digit = |c: char| DIGITS.iter().position(|&digit| digit as char == c);
+
+    let mut cells = Vec::new();
+    let (mut x, mut strip) = (0, 0);
+    let mut chars = encoding.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            'w' => x += 2,
+            'x' => x += 3,
+            'y' => {
+                let run = chars.next().and_then(digit)
+                    .ok_or_else(|| "'y' must be followed by a digit or lowercase letter".to_string())?;
+                x += 4 + run as i64;
+            },
+            'z' => {
+                x = 0;
+                strip += 1;
+            },
+            c => {
+                let column = digit(c).ok_or_else(|| format!("unexpected character {:?}", c))?;
+
+                for row in 0..STRIP_HEIGHT {
+                    if column & (1 << row) != 0 {
+                        cells.push((x, strip * STRIP_HEIGHT + row));
+                    }
+                }
+
+                x += 1;
+            },
+        }
+    }
+
+    Ok(cells)
+}
+
+/// Returns the apgcode of the cycle that the live [cells] settle into under
+/// [rule], on an unbounded plane, or None if they don't repeat within
+/// MAX_GENERATIONS generations. [rule] must not be a B0 rule.
+pub(crate) fn classify(cells: &[Point], rule: &Rule) -> Option<String> {
+    // Maps each generation, normalized, to when it was first seen and where
+    // its top-left corner was.
+    let mut seen: HashMap<Vec<Point>, (usize, Point)> = HashMap::new();
+    let mut phases = Vec::new();
+    let mut cells = cells.to_vec();
+
+    for generation in 0..=MAX_GENERATIONS {
+        if cells.len() > MAX_POPULATION {
+            break;
+        }
+
+        let top_left = (
+            cells.iter().map(|&(x, _)| x).min().unwrap_or(0),
+            cells.iter().map(|&(_, y)| y).min().unwrap_or(0),
+        );
+        let normalized = normalize(&cells);
+
+        if let Some(&(first, first_top_left)) = seen.get(&normalized) {
+            let period = generation - first;
+            let encoding = canonical_encoding(&phases[first..]);
+
+            return Some(if top_left != first_top_left {
+                format!("xq{}_{}", period, encoding)
+            } else if period == 1 {
+                format!("xs{}_{}", normalized.len(), encoding)
+            } else {
+                format!("xp{}_{}", period, encoding)
+            });
+        }
+
+        seen.insert(normalized.clone(), (generation, top_left));
+        phases.push(normalized);
+        cells = step(&cells, rule);
+    }
+
+    None
+}
+
+/// Returns the generation after the live [cells] under [rule], which must not
+/// be a B0 rule.
+fn step(cells: &[Point], rule: &Rule) -> Vec<Point> {
+    let live: HashSet<Point> = cells.iter().copied().collect();
+
+    // Only live cells and their neighbors can be alive next generation.
+    let candidates: HashSet<Point> = cells.iter()
+        .flat_map(|&(x, y)| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy))))
+        .collect();
+
+    candidates.into_iter()
+        .filter(|&(x, y)| {
+            let mut neighborhood = 0;
+
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if live.contains(&(x + dx, y + dy)) {
+                        neighborhood |= 1 << (3 * (dy + 1) + (dx + 1));
+                    }
+                }
+            }
+
+            rule.next_state(neighborhood) == Cell::Alive
+        })
+        .collect()
+}
+
+impl Pattern {
+    /// Decodes the apgcode of a still life (e.g. "xs4_33"), oscillator
+    /// ("xp2_7") or spaceship ("xq4_153"), as used by Catagolue. The pattern
+    /// is in the phase and orientation that the apgcode encodes.
+    pub fn from_apgcode(apgcode: &str) -> Result<Pattern, String> {
+        let err = |reason: String| format!("Invalid apgcode {:?}: {}", apgcode, reason);
+
+        let (prefix, encoding) = apgcode.trim().split_once('_')
+            .ok_or_else(|| err("expected the form xs<population>_<cells>, xp<period>_<cells> or xq<period>_<cells>".into()))?;
+
+        let (kind, number) = prefix.split_at(prefix.len().min(2));
+        if !["xs", "xp", "xq"].contains(&kind) {
+            return Err(err(format!("{:?} patterns can't be decoded; only xs, xp and xq can", kind)));
+        }
+
+        let number: usize = number.parse().map_err(|_| err(format!("{:?} is not a population or period", number)))?;
+        let cells = decode(encoding).map_err(err)?;
+
+        if kind == "xs" && cells.len() != number {
+            return Err(err(format!("the cells have a population of {}, not {}", cells.len(), number)));
+        }
+
+        let live_cells: Vec<(u32, u32)> = cells.iter()
+            .map(|&(x, y)| Ok((u32::try_from(x)?, u32::try_from(y)?)))
+            .collect::<Result<_, std::num::TryFromIntError>>()
+            .map_err(|_| err("the pattern is too large".into()))?;
+
+        Pattern::from_live_cells(&live_cells).map_err(err)
+    }
+
+    /// Returns the apgcode of the cycle that this settles into when run on an
+    /// unbounded plane under its rule (or Conway's, if it has none).
+    pub fn to_apgcode(&self) -> Result<String, String> {
+        let rule = self.rule.unwrap_or_default();
+
+        if rule.has_b0() {
+            return Err(format!("apgcodes can't be computed for B0 rules, such as {}", rule));
+        }
+
+        if let Some(family) = rule.family() {
+            return Err(format!("apgcodes can't be computed for {} rules, such as {}", family, rule));
+        }
+
+        // Objects are identified up to rotation and reflection, which only
+        // preserves how they evolve under isotropic rules.
+        if !rule.is_isotropic() {
+            return Err(format!("apgcodes can't be computed for anisotropic rules, such as {}", rule));
+        }
+
+        let cells: Vec<Point> = self.live_cells().into_iter().map(|(x, y)| (i64::from(x), i64::from(y))).collect();
+
+        classify(&cells, &rule)
+            .ok_or_else(|| format!("The pattern doesn't repeat within {} generations", MAX_GENERATIONS))
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Loads the pattern described by [apgcode] (e.g. "xq4_153" for a glider)
+    /// into this with its top-left corner at ([x], [y]).
+    pub fn load_apgcode(&mut self, apgcode: &str, x: u32, y: u32) -> Result<(), String> {
+        self.paste(&Pattern::from_apgcode(apgcode)?, x, y);
+        Ok(())
+    }
+
+    /// Creates a universe just large enough to hold the pattern described by [apgcode].
+    pub fn from_apgcode(apgcode: &str) -> Result<Universe, String> {
+        Ok(Universe::from_pattern(&Pattern::from_apgcode(apgcode)?))
+    }
+
+    /// Returns the apgcode of the still life, oscillator or spaceship that the
+    /// cells of this settle into, when run on an unbounded plane under the
+    /// current rule.
+    pub fn to_apgcode(&self) -> Result<String, String> {
+        self.to_pattern().to_apgcode()
+    }
+
+    /// Returns the apgcode of the cells in the [width] x [height] region of
+    /// this with its top-left corner at ([x], [y]), as in [to_apgcode]. The
+    /// region must be within the edges of this.
+    pub fn region_to_apgcode(&self, x: u32, y: u32, width: u32, height: u32) -> Result<String, String> {
+        self.region_to_pattern(x, y, width, height)?.to_apgcode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Pattern, Universe};
+
+    /// Returns the apgcode of the pattern described by [rle].
+    fn apgcode_of(rle: &str) -> Result<String, String> {
+        Pattern::from_rle(rle)?.to_apgcode()
+    }
+
+    #[test]
+    fn classifies_objects() {
+        assert_eq!(apgcode_of("x = 2, y = 2\n2o$2o!").as_deref(), Ok("xs4_33"));
+        assert_eq!(apgcode_of("x = 3, y = 1\n3o!").as_deref(), Ok("xp2_7"));
+        assert_eq!(apgcode_of("x = 3, y = 3\nbo$2bo$3o!").as_deref(), Ok("xq4_153"));
+
+        // Any phase and orientation gives the same code.
+        assert_eq!(apgcode_of("x = 1, y = 3\no$o$o!").as_deref(), Ok("xp2_7"));
+        assert_eq!(apgcode_of("x = 3, y = 3\nobo$b2o$bo!").as_deref(), Ok("xq4_153"));
+    }
+
+    #[test]
+    fn round_trips_apgcodes() {
+        for apgcode in ["xs4_33", "xs6_356", "xp2_7", "xp2_7e", "xq4_153", "xq4_27deee6"] {
+            assert_eq!(Pattern::from_apgcode(apgcode).unwrap().to_apgcode().as_deref(), Ok(apgcode), "{}", apgcode);
+            assert_eq!(Universe::from_apgcode(apgcode).unwrap().to_apgcode().as_deref(), Ok(apgcode), "{}", apgcode);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_apgcodes() {
+        for apgcode in ["xs4", "xs5_33", "yl144_1_16_afb", "xs_33", "xp2_7!"] {
+            assert!(Pattern::from_apgcode(apgcode).is_err(), "{}", apgcode);
+        }
+
+        let mut pattern = Pattern::from_apgcode("xs4_33").unwrap();
+        pattern.rule = Some("B0/S8".parse().unwrap());
+        assert!(pattern.to_apgcode().is_err());
+    }
+}
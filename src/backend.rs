@@ -0,0 +1,141 @@
+use wasm_bindgen::prelude::*;
+
+use crate::bitgrid::BitGrid;
+use crate::tiles::Tiles;
+use crate::Cell;
+
+/// How a [Universe] stores its cells.
+#[wasm_bindgen]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// One byte per cell: its state. The only backend that can hold the
+    /// dying states of Generations rules.
+    #[default]
+    Bytes = 0,
+
+    /// 64 cells per u64, ticked 64 cells at a time. Best for large universes.
+    BitPacked = 1,
+}
+
+/// The cells of a [Universe], laid out as chosen by its [Backend].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Cells {
+    /// Row-major states, as described by [Rule].
+    Bytes(Vec<u8>),
+    BitPacked(BitGrid),
+}
+
+impl Cells {
+    /// Stores the row-major [states] of a [width] x [height] grid using
+    /// [backend]. Dying states are lost if [backend] is Backend::BitPacked.
+    pub fn new(backend: Backend, states: Vec<u8>, width: u32, height: u32) -> Cells {
+        match backend {
+            Backend::Bytes => Cells::Bytes(states),
+            Backend::BitPacked => {
+                let cells: Vec<Cell> = states.into_iter().map(Cell::from_state).collect();
+                Cells::BitPacked(BitGrid::from_cells(&cells, width, height))
+            },
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self {
+            Cells::Bytes(_) => Backend::Bytes,
+            Cells::BitPacked(_) => Backend::BitPacked,
+        }
+    }
+
+    /// Returns a row-major copy of the states of these cells.
+    pub fn to_vec(&self) -> Vec<u8> {
+        match self {
+            Cells::Bytes(states) => states.clone(),
+            Cells::BitPacked(grid) => grid.to_cells().into_iter().map(|cell| cell as u8).collect(),
+        }
+    }
+
+    /// Returns the cell at ([x], [y]), which must be within the
+    /// [width]-column grid. Dying cells are Cell::Dead.
+    pub fn get(&self, x: u32, y: u32, width: u32) -> Cell {
+        match self {
+            Cells::Bytes(states) => Cell::from_state(states[(y * width + x) as usize]),
+            Cells::BitPacked(grid) => grid.get(x, y),
+        }
+    }
+
+    /// Returns the state of the cell at ([x], [y]), as for [get].
+    pub fn get_state(&self, x: u32, y: u32, width: u32) -> u8 {
+        match self {
+            Cells::Bytes(states) => states[(y * width + x) as usize],
+            Cells::BitPacked(grid) => grid.get(x, y) as u8,
+        }
+    }
+
+    /// Returns the cells in the tiles flagged in [flagged] whose states differ
+    /// between this and [next], as (x, y, state, next state).
+    pub fn changes<'a>(&'a self, next: &'a Cells, tiles: &'a Tiles, flagged: &'a [bool]) -> impl Iterator<Item = (u32, u32, u8, u8)> + 'a {
+        let width = tiles.width();
+
+        tiles.cells_in(flagged)
+            .map(move |(x, y)| (x, y, self.get_state(x, y, width), next.get_state(x, y, width)))
+            .filter(|&(_, _, state, next)| state != next)
+    }
+
+    /// Sets the state of the cell at ([x], [y]). Dying states become
+    /// Cell::Dead if these are bit-packed.
+    pub fn set_state(&mut self, x: u32, y: u32, width: u32, state: u8) {
+        match self {
+            Cells::Bytes(states) => states[(y * width + x) as usize] = state,
+            Cells::BitPacked(grid) => grid.set(x, y, Cell::from_state(state)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Backend;
+    use crate::{Topology, Universe};
+
+    fn soup(width: u32, height: u32, topology: Topology, rule: &str, backend: Backend) -> Universe {
+        let mut universe = Universe::empty(width, height);
+        universe.set_topology(topology);
+        universe.set_rule(rule).unwrap();
+        universe.set_backend(backend);
+        universe.randomize_region(width / 4, height / 4, width / 2, height / 2, 0.4, 7).unwrap();
+
+        universe
+    }
+
+    #[test]
+    fn bit_packed_ticks_match_bytes() {
+        let topologies = [Topology::Torus, Topology::Plane, Topology::Cylinder, Topology::KleinBottle, Topology::CrossSurface];
+
+        for &topology in &topologies {
+            for rule in &["B3/S23", "B36/S23", "B2-a/S12"] {
+                let mut bytes = soup(130, 50, topology, rule, Backend::Bytes);
+                let mut bit_packed = soup(130, 50, topology, rule, Backend::BitPacked);
+
+                for generation in 0..100 {
+                    bytes.tick();
+                    bit_packed.tick();
+
+                    assert_eq!(bit_packed.cells.to_vec(), bytes.cells.to_vec(), "{:?} {} generation {}", topology, rule, generation);
+                    assert_eq!(bit_packed.births(), bytes.births());
+                    assert_eq!(bit_packed.deaths(), bytes.deaths());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn switching_backends_keeps_cells() {
+        let mut universe = soup(100, 40, Topology::Torus, "B3/S23", Backend::Bytes);
+        let cells = universe.cells.to_vec();
+
+        universe.set_backend(Backend::BitPacked);
+        assert_eq!(universe.cells.to_vec(), cells);
+
+        universe.set_backend(Backend::Bytes);
+        assert_eq!(universe.cells.to_vec(), cells);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::stats::TickCounts;
+use crate::tiles::TILE_SIZE;
+use crate::Cell;
+
+const WORD_BITS: u32 = TILE_SIZE;
+
+/// Cells packed 64 to a u64. Each row starts on a new word, and bit i of a
+/// word holds the cell i columns right of the word's first cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitGrid {
+    width: u32,
+    height: u32,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    /// Creates a [width] x [height] grid of dead cells.
+    pub fn new(width: u32, height: u32) -> BitGrid {
+        let words_per_row = width.div_ceil(WORD_BITS) as usize;
+
+        BitGrid {
+            width,
+            height,
+            words_per_row,
+            words: vec![0; words_per_row * height as usize],
+        }
+    }
+
+    /// Packs the row-major [cells] of a [width] x [height] grid.
+    pub fn from_cells(cells: &[Cell], width: u32, height: u32) -> BitGrid {
+        let mut grid = BitGrid::new(width, height);
+
+        for (idx, &cell) in cells.iter().enumerate() {
+            let idx = idx as u32;
+            grid.set(idx % width, idx / width, cell);
+        }
+
+        grid
+    }
+
+    /// Unpacks this into row-major cells.
+    pub fn to_cells(&self) -> Vec<Cell> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(x, y))
+            .collect()
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Cell {
+        let (word, bit) = self.locate(x, y);
+
+        if self.words[word] & bit != 0 {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, cell: Cell) {
+        let (word, bit) = self.locate(x, y);
+
+        match cell {
+            Cell::Alive => self.words[word] |= bit,
+            Cell::Dead => self.words[word] &= !bit,
+        }
+    }
+
+    /// Writes the next generation of this into [out] under the outer-totalistic
+    /// rule with the birth and survival [masks] of neighbor counts, treating
+    /// cells beyond the edges as dead. [out] must have the same size as this.
+    ///
+    /// Only the tiles flagged in [recompute] are written, and each tile that
+    /// changes is flagged in [changed]. A tile is one word wide, so a row of
+    /// words is also a row of tiles. Births and deaths are added to [counts].
+    ///
+    /// Each word's neighbor counts are computed 64 cells at a time by adding
+    /// shifted copies of the surrounding rows with a bit-sliced adder.
+    pub fn step_into(&self, (births, survivals): (u16, u16), recompute: &[bool], changed: &mut [bool], counts: &mut TickCounts, out: &mut BitGrid) {
+        let row_count = self.words_per_row;
+        let last_word_mask = match self.width % WORD_BITS {
+            0 => !0,
+            used_bits => (1 << used_bits) - 1,
+        };
+
+        for y in 0..self.height as usize {
+            let row = |y: Option<usize>| -> &[u64] {
+                match y {
+                    Some(y) if y < self.height as usize => &self.words[y * row_count..(y + 1) * row_count],
+                    _ => &[],
+                }
+            };
+
+            let above = row(y.checked_sub(1));
+            let current = row(Some(y));
+            let below = row(Some(y + 1));
+
+            for i in 0..row_count {
+                let tile = (y / TILE_SIZE as usize) * row_count + i;
+                if !recompute[tile] {
+                    continue;
+                }
+
+                // Returns (west neighbors, cells, east neighbors) of word i in [row].
+                let shifted = |row: &[u64]| -> (u64, u64, u64) {
+                    let word = |i: Option<usize>| i.and_then(|i| row.get(i)).copied().unwrap_or(0);
+                    let (prev, cur, next) = (word(i.checked_sub(1)), word(Some(i)), word(Some(i + 1)));
+
+                    ((cur << 1) | (prev >> 63), cur, (cur >> 1) | (next << 63))
+                };
+
+                let (above_w, above_c, above_e) = shifted(above);
+                let (cur_w, cur_c, cur_e) = shifted(current);
+                let (below_w, below_c, below_e) = shifted(below);
+
+                // Sum the eight neighbors into the bits of a 4-bit count.
+                let (above_ones, above_twos) = full_add(above_w, above_c, above_e);
+                let (below_ones, below_twos) = full_add(below_w, below_c, below_e);
+                let (mid_ones, mid_twos) = (cur_w ^ cur_e, cur_w & cur_e);
+
+                let (bit0, ones_carry) = full_add(above_ones, below_ones, mid_ones);
+                let (twos, twos_carry) = full_add(above_twos, below_twos, mid_twos);
+                let (bit1, bit1_carry) = (twos ^ ones_carry, twos & ones_carry);
+                let (bit2, bit3) = (twos_carry ^ bit1_carry, twos_carry & bit1_carry);
+
+                let mut next = 0;
+                for count in 0..=8 {
+                    let gives_birth = births & (1 << count) != 0;
+                    let gives_survival = survivals & (1 << count) != 0;
+
+                    if !gives_birth && !gives_survival {
+                        continue;
+                    }
+
+                    let bit_matches = |bit: u64, place: u32| if count & (1 << place) != 0 { bit } else { !bit };
+                    let has_count = bit_matches(bit0, 0) & bit_matches(bit1, 1) & bit_matches(bit2, 2) & bit_matches(bit3, 3);
+
+                    if gives_birth {
+                        next |= has_count & !cur_c;
+                    }
+                    if gives_survival {
+                        next |= has_count & cur_c;
+                    }
+                }
+
+                if i + 1 == row_count {
+                    next &= last_word_mask;
+                }
+
+                changed[tile] |= next != cur_c;
+                counts.births += (next & !cur_c).count_ones();
+                counts.deaths += (cur_c & !next).count_ones();
+                out.words[y * row_count + i] = next;
+            }
+        }
+    }
+
+    fn locate(&self, x: u32, y: u32) -> (usize, u64) {
+        let word = y as usize * self.words_per_row + (x / WORD_BITS) as usize;
+
+        (word, 1 << (x % WORD_BITS))
+    }
+}
+
+/// Adds three bitboards, returning the (sum, carry) bitboards.
+fn full_add(a: u64, b: u64, c: u64) -> (u64, u64) {
+    let partial = a ^ b;
+
+    (partial ^ c, (a & b) | (partial & c))
+}
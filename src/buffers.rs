@@ -0,0 +1,135 @@
+use wasm_bindgen::prelude::*;
+
+use crate::backend::Cells;
+use crate::Universe;
+
+/// Indices of the cells that changed since the buffer was last cleared, so
+/// that JS can redraw just those.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ChangedCells {
+    enabled: bool,
+
+    /// Row-major indices, possibly repeated.
+    indices: Vec<u32>,
+
+    /// Whether cells changed in ways that [indices] doesn't describe (the
+    /// universe was resized or replaced, or too many cells changed), so all
+    /// cells should be treated as changed.
+    all: bool,
+
+    /// Generation at which this was last cleared.
+    since: u64,
+
+    /// [indices] is abandoned in favor of [all] beyond this length.
+    max_len: usize,
+}
+
+impl ChangedCells {
+    pub fn new(cell_count: usize) -> ChangedCells {
+        ChangedCells {
+            enabled: false,
+            indices: Vec::new(),
+            all: true,
+            since: 0,
+            max_len: cell_count,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.indices = Vec::new();
+        self.all = true;
+    }
+
+    pub fn record(&mut self, idx: u32) {
+        if !self.enabled || self.all {
+            return;
+        }
+
+        if self.indices.len() < self.max_len {
+            self.indices.push(idx);
+        } else {
+            self.indices = Vec::new();
+            self.all = true;
+        }
+    }
+
+    /// Marks all cells as changed, for a universe with [cell_count] cells.
+    pub fn record_all(&mut self, cell_count: usize) {
+        self.max_len = cell_count;
+        self.indices = Vec::new();
+        self.all = true;
+    }
+
+    pub fn clear(&mut self, generation: u64) {
+        self.indices.clear();
+        self.all = false;
+        self.since = generation;
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Returns a pointer to the states (see [get_state_at]) of width * height
+    /// row-major cells, one byte each, for viewing from JS as a Uint8Array over
+    /// wasm memory. The pointer is only valid until the next call that changes
+    /// this, so should be fetched again after each tick or edit.
+    pub fn cells_ptr(&mut self) -> *const u8 {
+        if let Cells::Bytes(cells) = &self.cells {
+            return cells.as_ptr();
+        }
+
+        self.unpacked_cells = self.cells.to_vec();
+        self.unpacked_cells.as_ptr()
+    }
+
+    /// Returns the number of cells at [cells_ptr].
+    pub fn cells_len(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+
+    /// Sets whether the indices of changed cells are collected for
+    /// [changed_cells_ptr]. Off by default. Enabling marks all cells as
+    /// changed.
+    pub fn set_track_changed_cells(&mut self, enabled: bool) {
+        self.changed_cells.set_enabled(enabled);
+    }
+
+    pub fn track_changed_cells(&self) -> bool {
+        self.changed_cells.is_enabled()
+    }
+
+    /// Returns a pointer to the row-major indices (as u32s) of the cells that
+    /// changed since [clear_changed_cells] was last called, for viewing from
+    /// JS as a Uint32Array. Indices may repeat. Like [cells_ptr], the pointer
+    /// should be fetched again after each tick or edit.
+    pub fn changed_cells_ptr(&self) -> *const u32 {
+        self.changed_cells.indices.as_ptr()
+    }
+
+    /// Returns the number of indices at [changed_cells_ptr].
+    pub fn changed_cells_len(&self) -> usize {
+        self.changed_cells.indices.len()
+    }
+
+    /// Returns whether all cells should be treated as changed, in which case
+    /// [changed_cells_ptr] lists none of them. True after tracking is enabled,
+    /// after this is resized, and when more cells changed than this has.
+    pub fn all_cells_changed(&self) -> bool {
+        self.changed_cells.all
+    }
+
+    /// Returns the generation at which [clear_changed_cells] was last called.
+    pub fn changed_cells_since(&self) -> u64 {
+        self.changed_cells.since
+    }
+
+    /// Empties the changed cells buffer, stamping it with the current generation.
+    pub fn clear_changed_cells(&mut self) {
+        self.changed_cells.clear(self.generation);
+    }
+}
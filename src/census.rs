@@ -0,0 +1,250 @@
+use std::collections::{HashMap, VecDeque};
+
+use wasm_bindgen::prelude::*;
+
+use crate::apgcode::{self, Point};
+use crate::{Cell, Rule, Universe};
+
+/// Code of objects that don't settle into a cycle.
+const PATHOLOGICAL: &str = "PATHOLOGICAL";
+
+/// Names of common objects under Conway's rules, by apgcode.
+const CONWAY_NAMES: &[(&str, &str)] = &[
+    ("xs4_33", "block"),
+    ("xs4_252", "tub"),
+    ("xs5_253", "boat"),
+    ("xs6_696", "beehive"),
+    ("xs6_356", "ship"),
+    ("xs6_25a4", "barge"),
+    ("xs7_2596", "loaf"),
+    ("xs7_25ac", "long boat"),
+    ("xs7_178c", "eater 1"),
+    ("xs8_6996", "pond"),
+    ("xs8_35ac", "long ship"),
+    ("xs8_3pm", "shillelagh"),
+    ("xp2_7", "blinker"),
+    ("xp2_7e", "toad"),
+    ("xp2_318c", "beacon"),
+    ("xp15_4r4z4r4", "pentadecathlon"),
+    ("xq4_153", "glider"),
+    ("xq4_6frc", "lightweight spaceship"),
+    ("xq4_27dee6", "middleweight spaceship"),
+    ("xq4_27deee6", "heavyweight spaceship"),
+];
+
+/// Which cells count as touching, and so belong to the same object.
+#[wasm_bindgen]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Connectivity {
+    /// Cells that share an edge.
+    Orthogonal = 0,
+
+    /// Cells that share an edge or a corner.
+    #[default]
+    Moore = 1,
+
+    /// Cells at most two cells apart horizontally and vertically.
+    Distance2 = 2,
+}
+
+impl Connectivity {
+    /// Returns the offsets of the cells that touch the cell at (0, 0).
+    fn offsets(self) -> Vec<Point> {
+        let radius = match self {
+            Connectivity::Orthogonal | Connectivity::Moore => 1,
+            Connectivity::Distance2 => 2,
+        };
+
+        (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| (dx, dy) != (0, 0))
+            .filter(|&(dx, dy)| self != Connectivity::Orthogonal || dx == 0 || dy == 0)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CensusEntry {
+    code: String,
+    count: u32,
+}
+
+/// The number of objects of each type in a universe, most common first.
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Census {
+    entries: Vec<CensusEntry>,
+
+    /// Whether the rule was Conway's, so that [name]s apply.
+    conway: bool,
+}
+
+#[wasm_bindgen]
+impl Census {
+    /// Returns the number of distinct object types.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the apgcode of the [idx]th most common object type, such as
+    /// "xs4_33" for a block, or "PATHOLOGICAL" for objects that didn't repeat.
+    /// Returns None (undefined, in JS) if [idx] is out of range.
+    pub fn code(&self, idx: usize) -> Option<String> {
+        self.entries.get(idx).map(|entry| entry.code.clone())
+    }
+
+    /// Returns the common name (e.g. "block") of the [idx]th most common object
+    /// type, if it has one and [idx] is in range.
+    pub fn name(&self, idx: usize) -> Option<String> {
+        if !self.conway {
+            return None;
+        }
+
+        let code = &self.entries.get(idx)?.code;
+        CONWAY_NAMES.iter().find(|(known, _)| known == code).map(|(_, name)| name.to_string())
+    }
+
+    /// Returns the number of objects of the [idx]th most common type, or None
+    /// if [idx] is out of range.
+    pub fn count(&self, idx: usize) -> Option<u32> {
+        self.entries.get(idx).map(|entry| entry.count)
+    }
+
+    /// Returns the number of objects with apgcode [code].
+    pub fn count_of(&self, code: &str) -> u32 {
+        self.entries.iter().find(|entry| entry.code == code).map_or(0, |entry| entry.count)
+    }
+
+    /// Returns the total number of objects.
+    pub fn total(&self) -> u32 {
+        self.entries.iter().map(|entry| entry.count).sum()
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Splits the live cells into objects of cells that touch according to
+    /// [connectivity], and counts the objects of each type.
+    ///
+    /// Each object is run on its own, on an unbounded plane, until it repeats,
+    /// and is identified by the apgcode of the cycle it settles into: xs for
+    /// still lifes, xp for oscillators and xq for spaceships, followed by the
+    /// period (or population, for still lifes) and the canonical encoding of
+    /// its phases under rotation and reflection.
+    pub fn census(&self, connectivity: Connectivity) -> Result<Census, String> {
+        if self.rule.has_b0() {
+            return Err(format!("Objects can't be run on their own under B0 rules, such as {}", self.rule));
+        }
+
+        if let Some(family) = self.rule.family() {
+            return Err(format!("Censuses aren't supported for {} rules, such as {}", family, self.rule));
+        }
+
+        if !self.rule.is_isotropic() {
+            return Err(format!("Censuses aren't supported for anisotropic rules, such as {}", self.rule));
+        }
+
+        let mut codes: HashMap<Vec<Point>, String> = HashMap::new();
+        let mut counts: HashMap<String, u32> = HashMap::new();
+
+        for object in self.objects(connectivity) {
+            let code = codes.entry(apgcode::normalize(&object))
+                .or_insert_with(|| apgcode::classify(&object, &self.rule).unwrap_or_else(|| PATHOLOGICAL.to_string()));
+
+            *counts.entry(code.clone()).or_insert(0) += 1;
+        }
+
+        let mut entries: Vec<CensusEntry> = counts.into_iter().map(|(code, count)| CensusEntry { code, count }).collect();
+        entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.code.cmp(&b.code)));
+
+        Ok(Census { entries, conway: self.rule == Rule::CONWAY })
+    }
+}
+
+// Private impl
+impl Universe {
+    /// Returns the live cells of each group of touching cells. Groups that
+    /// wrap around the edges are unwrapped, so the coordinates of a group's
+    /// cells can fall outside of this.
+    fn objects(&self, connectivity: Connectivity) -> Vec<Vec<Point>> {
+        let offsets = connectivity.offsets();
+        let mut visited = vec![false; (self.width * self.height) as usize];
+        let mut objects = Vec::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if visited[(y * self.width + x) as usize] || self.cells.get(x, y, self.width) != Cell::Alive {
+                    continue;
+                }
+
+                // Breadth-first search, tracking both the cell on this' surface
+                // and where it is relative to the first cell found.
+                let mut object = Vec::new();
+                let mut queue = VecDeque::new();
+
+                visited[(y * self.width + x) as usize] = true;
+                queue.push_back(((x, y), (i64::from(x), i64::from(y))));
+
+                while let Some(((x, y), unwrapped)) = queue.pop_front() {
+                    object.push(unwrapped);
+
+                    for &(dx, dy) in &offsets {
+                        let neighbor = match self.resolve(i64::from(x) + dx, i64::from(y) + dy) {
+                            Some(neighbor) => neighbor,
+                            None => continue,
+                        };
+
+                        let idx = (neighbor.1 * self.width + neighbor.0) as usize;
+                        if !visited[idx] && self.cells.get(neighbor.0, neighbor.1, self.width) == Cell::Alive {
+                            visited[idx] = true;
+                            queue.push_back((neighbor, (unwrapped.0 + dx, unwrapped.1 + dy)));
+                        }
+                    }
+                }
+
+                objects.push(object);
+            }
+        }
+
+        objects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Connectivity;
+    use crate::Universe;
+
+    #[test]
+    fn counts_objects() {
+        let mut universe = Universe::empty(40, 20);
+        universe.load_rle("x = 2, y = 2\n2o$2o!", 2, 2).unwrap();
+        universe.load_rle("x = 2, y = 2\n2o$2o!", 10, 2).unwrap();
+        universe.load_rle("x = 3, y = 1\n3o!", 20, 10).unwrap();
+
+        let census = universe.census(Connectivity::Moore).unwrap();
+
+        assert_eq!(census.len(), 2);
+        assert_eq!(census.total(), 3);
+        assert_eq!(census.code(0).as_deref(), Some("xs4_33"));
+        assert_eq!(census.name(0).as_deref(), Some("block"));
+        assert_eq!(census.count(0), Some(2));
+        assert_eq!(census.code(1).as_deref(), Some("xp2_7"));
+        assert_eq!(census.count_of("xp2_7"), 1);
+    }
+
+    #[test]
+    fn out_of_range_entries_are_none() {
+        let census = Universe::empty(8, 8).census(Connectivity::Moore).unwrap();
+
+        assert!(census.is_empty());
+        assert_eq!(census.code(0), None);
+        assert_eq!(census.name(0), None);
+        assert_eq!(census.count(usize::MAX), None);
+    }
+}
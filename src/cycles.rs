@@ -0,0 +1,654 @@
+use std::collections::{HashMap, VecDeque};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Topology, Universe};
+
+/// Whether a universe's generations have started repeating.
+#[wasm_bindgen]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CycleStatus {
+    /// No generation within the window repeats the current one.
+    #[default]
+    Evolving = 0,
+
+    /// The current generation is the same as the one before it.
+    Stable = 1,
+
+    /// The current generation repeats one more than a generation ago.
+    Oscillating = 2,
+
+    /// The current generation repeats an earlier one, but moved.
+    Spaceship = 3,
+}
+
+/// A repeating sequence of generations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Cycle {
+    pub period: u64,
+
+    /// First generation of the cycle.
+    pub start: u64,
+
+    /// How far the pattern moves each period.
+    pub dx: i64,
+    pub dy: i64,
+}
+
+impl Cycle {
+    /// Describes how fast and in which direction this moves, in terms of the
+    /// speed of light (c), e.g. "c/4 diagonal" or "(2,1)c/6".
+    pub fn speed(&self) -> String {
+        let (long, short) = (self.dx.unsigned_abs().max(self.dy.unsigned_abs()), self.dx.unsigned_abs().min(self.dy.unsigned_abs()));
+
+        if short != 0 && short != long {
+            return format!("({},{})c/{}", long, short, self.period);
+        }
+
+        let divisor = gcd(long, self.period);
+        let (numerator, denominator) = (long / divisor, self.period / divisor);
+
+        let fraction = match (numerator, denominator) {
+            (1, 1) => "c".to_string(),
+            (1, denominator) => format!("c/{}", denominator),
+            (numerator, 1) => format!("{}c", numerator),
+            (numerator, denominator) => format!("{}c/{}", numerator, denominator),
+        };
+
+        let direction = if short == 0 { "orthogonal" } else { "diagonal" };
+        format!("{} {}", fraction, direction)
+    }
+}
+
+/// Arbitrary base of shape hashes for y.
+const Y_BASE: u64 = 0x0b5a_d4ec_eda1_ce2a;
+
+/// A hash of the states of the cells that aren't dead, relative to their
+/// bounding box, which can be updated one changed cell at a time.
+///
+/// The cells are hashed as the sum of state * x_base^x * y_base^y over their
+/// coordinates, modulo a prime, so that dying cells of Generations rules are
+/// told apart from live ones and from each other. Moving them by (dx, dy)
+/// multiplies that sum by x_base^dx * y_base^dy, so dividing by the powers
+/// for the bounding box's top-left corner gives the same hash wherever they
+/// are.
+///
+/// x_base is a width-th root of unity, so this also holds for moves that wrap
+/// around the left/right edges. For the top/bottom edges, the rows above the
+/// top of the bounding box are instead moved below it, using per-row sums.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Shape {
+    modulus: u64,
+    sum: u64,
+    row_sums: Vec<u64>,
+
+    /// Number of cells that aren't dead in each column and row, to find the
+    /// bounding box.
+    column_counts: Vec<u32>,
+    row_counts: Vec<u32>,
+
+    /// x_base^0 through x_base^(width - 1), and likewise for y.
+    x_powers: Vec<u64>,
+    y_powers: Vec<u64>,
+}
+
+impl Shape {
+    /// Creates the shape of an empty [width] x [height] grid.
+    fn new(width: u32, height: u32) -> Shape {
+        let (width, height) = (u64::from(width), u64::from(height));
+        let modulus = prime_modulus(width.max(1));
+
+        Shape {
+            modulus,
+            sum: 0,
+            row_sums: vec![0; height as usize],
+
+            column_counts: vec![0; width as usize],
+            row_counts: vec![0; height as usize],
+
+            x_powers: powers(root_of_unity(width.max(1), modulus), width, modulus),
+            y_powers: powers(Y_BASE % modulus, height, modulus),
+        }
+    }
+
+    fn record_change(&mut self, x: u32, y: u32, before: u8, after: u8) {
+        let (x, y) = (x as usize, y as usize);
+        let term = mul_mod(self.x_powers[x], self.y_powers[y], self.modulus);
+
+        let added = mul_mod(term, u64::from(after), self.modulus);
+        let removed = mul_mod(term, u64::from(before), self.modulus);
+        let term = (added + self.modulus - removed) % self.modulus;
+        let count_change = i32::from(after != 0) - i32::from(before != 0);
+
+        self.sum = (self.sum + term) % self.modulus;
+        self.row_sums[y] = (self.row_sums[y] + term) % self.modulus;
+        self.column_counts[x] = (self.column_counts[x] as i32 + count_change) as u32;
+        self.row_counts[y] = (self.row_counts[y] as i32 + count_change) as u32;
+    }
+
+    /// Returns the top-left corner of the bounding box of the live cells. Along
+    /// an edge that [wraps], the box starts after the longest run of empty
+    /// columns (or rows), which may be on the other side of the edge.
+    fn top_left(&self, wraps: (bool, bool)) -> (u32, u32) {
+        (first_occupied(&self.column_counts, wraps.0), first_occupied(&self.row_counts, wraps.1))
+    }
+
+    /// Returns the hash with the top-left corner of the bounding box at [top_left].
+    fn hash(&self, (left, top): (u32, u32)) -> u64 {
+        let modulus = self.modulus;
+        let height = self.row_sums.len() as u64;
+
+        // Rows above the top are moved below the bottom, by multiplying them
+        // by y_base^height.
+        let above: u64 = self.row_sums[..top as usize].iter().fold(0, |sum, &row_sum| (sum + row_sum) % modulus);
+        let wrap_factor = (pow_mod(Y_BASE, height, modulus) + modulus - 1) % modulus;
+        let sum = (self.sum + mul_mod(above, wrap_factor, modulus)) % modulus;
+
+        let x_inverse = match left {
+            0 => 1,
+            left => self.x_powers[self.x_powers.len() - left as usize],
+        };
+        let y_inverse = pow_mod(Y_BASE, modulus - 1 - u64::from(top), modulus);
+
+        mul_mod(mul_mod(sum, x_inverse, modulus), y_inverse, modulus)
+    }
+}
+
+/// Returns the index of the first nonzero count, or if [wraps], the first one
+/// after the longest run of zeros, counting runs that wrap around the end.
+/// 0 if all counts are zero.
+fn first_occupied(counts: &[u32], wraps: bool) -> u32 {
+    let first = match counts.iter().position(|&count| count > 0) {
+        Some(first) => first,
+        None => return 0,
+    };
+
+    if !wraps {
+        return first as u32;
+    }
+
+    // Walk once around, starting at an occupied index, so that every run of
+    // zeros is seen whole.
+    let (mut best, mut best_length, mut run_length) = (first, 0, 0);
+    for offset in 1..=counts.len() {
+        let idx = (first + offset) % counts.len();
+
+        if counts[idx] == 0 {
+            run_length += 1;
+        } else {
+            if run_length > best_length {
+                best = idx;
+                best_length = run_length;
+            }
+
+            run_length = 0;
+        }
+    }
+
+    best as u32
+}
+
+/// The shape hash of a generation, and where its bounding box was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Observation {
+    hash: u64,
+    generation: u64,
+    top_left: (u32, u32),
+}
+
+/// Finds generations that repeat, possibly moved, by remembering the shape
+/// hashes of the most recent ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Cycles {
+    /// Number of generations remembered. 0 disables detection.
+    window: u32,
+
+    /// Shape of the current cells. None while disabled.
+    shape: Option<Shape>,
+
+    /// Observations of consecutive generations, oldest first.
+    recent: VecDeque<Observation>,
+
+    /// Maps each hash in [recent] to the latest generation that had it.
+    latest: HashMap<u64, u64>,
+
+    /// The cycle the current generation is part of, if any.
+    cycle: Option<Cycle>,
+}
+
+impl Cycles {
+    pub fn new(window: u32) -> Cycles {
+        Cycles {
+            window,
+            shape: None,
+
+            recent: VecDeque::new(),
+            latest: HashMap::new(),
+
+            cycle: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.window > 0
+    }
+
+    pub fn window(&self) -> u32 {
+        self.window
+    }
+
+    /// Sets the number of generations remembered. The caller is responsible
+    /// for [rebuild]ing the shape if this enables detection.
+    pub fn set_window(&mut self, window: u32) {
+        self.window = window;
+        self.reset();
+
+        if !self.is_enabled() {
+            self.shape = None;
+        }
+    }
+
+    /// Recomputes the shape, if enabled, for a [width] x [height] grid whose
+    /// cells that aren't dead are [states], as (x, y, state).
+    pub fn rebuild(&mut self, width: u32, height: u32, states: impl Iterator<Item = (u32, u32, u8)>) {
+        self.reset();
+
+        if self.is_enabled() {
+            let mut shape = Shape::new(width, height);
+            for (x, y, state) in states {
+                shape.record_change(x, y, Cell::Dead as u8, state);
+            }
+
+            self.shape = Some(shape);
+        }
+    }
+
+    /// Updates the shape for the cell at ([x], [y]) going from state [before]
+    /// to [after].
+    pub fn record_change(&mut self, x: u32, y: u32, before: u8, after: u8) {
+        if let Some(shape) = &mut self.shape {
+            shape.record_change(x, y, before, after);
+        }
+    }
+
+    /// Forgets all remembered generations, e.g. because the cells were edited
+    /// and so no longer follow from them.
+    pub fn reset(&mut self) {
+        self.recent.clear();
+        self.latest.clear();
+        self.cycle = None;
+    }
+
+    /// Remembers the current shape as that of [generation], checking whether
+    /// it repeats a remembered generation. Does nothing if [generation] was
+    /// already observed, or if disabled.
+    pub fn observe(&mut self, generation: u64, topology: Topology) {
+        let shape = match &self.shape {
+            Some(shape) => shape,
+            None => return,
+        };
+
+        if self.recent.back().map(|observation| observation.generation) == Some(generation) {
+            return;
+        }
+
+        let top_left = shape.top_left(topology.wraps());
+        let observation = Observation { hash: shape.hash(top_left), generation, top_left };
+
+        self.cycle = self.latest.get(&observation.hash).map(|&seen| {
+            let period = generation - seen;
+            let (dx, dy) = self.displacement(&self.observation(seen).expect("latest should only hold recent generations"), &observation, topology);
+
+            // The same cycle as last generation started at the same time.
+            let start = match self.cycle {
+                Some(last) if (last.period, last.dx, last.dy) == (period, dx, dy) => last.start,
+                _ => self.cycle_start(period, (dx, dy), topology, seen),
+            };
+
+            Cycle { period, start, dx, dy }
+        });
+
+        self.recent.push_back(observation);
+        self.latest.insert(observation.hash, generation);
+
+        while self.recent.len() > self.window as usize {
+            let oldest = self.recent.pop_front().expect("recent should be longer than window");
+
+            if self.latest.get(&oldest.hash) == Some(&oldest.generation) {
+                self.latest.remove(&oldest.hash);
+            }
+        }
+    }
+
+    pub fn status(&self) -> CycleStatus {
+        match self.cycle {
+            None => CycleStatus::Evolving,
+            Some(cycle) if cycle.dx != 0 || cycle.dy != 0 => CycleStatus::Spaceship,
+            Some(cycle) if cycle.period == 1 => CycleStatus::Stable,
+            Some(_) => CycleStatus::Oscillating,
+        }
+    }
+
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+
+    fn observation(&self, generation: u64) -> Option<Observation> {
+        let oldest = self.recent.front()?.generation;
+        let offset = generation.checked_sub(oldest)?;
+
+        self.recent.get(offset as usize).copied()
+    }
+
+    /// Returns how far the bounding box moved from [from] to [to]. Along edges
+    /// that wrap, this is the shorter way around.
+    fn displacement(&self, from: &Observation, to: &Observation, topology: Topology) -> (i64, i64) {
+        let shape = self.shape.as_ref().expect("displacements should only be computed while enabled");
+        let (wraps_x, wraps_y) = topology.wraps();
+
+        let offset = |from: u32, to: u32, size: usize, wraps: bool| -> i64 {
+            let (offset, size) = (i64::from(to) - i64::from(from), size as i64);
+
+            if wraps {
+                (offset + size / 2).rem_euclid(size) - size / 2
+            } else {
+                offset
+            }
+        };
+
+        (
+            offset(from.top_left.0, to.top_left.0, shape.column_counts.len(), wraps_x),
+            offset(from.top_left.1, to.top_left.1, shape.row_counts.len(), wraps_y),
+        )
+    }
+
+    /// Returns the earliest remembered generation from which every generation
+    /// repeats [period] generations later, moved by [displacement], given that
+    /// the current generation repeats [seen].
+    fn cycle_start(&self, period: u64, displacement: (i64, i64), topology: Topology, seen: u64) -> u64 {
+        let repeats = |generation: u64| -> bool {
+            match (self.observation(generation), self.observation(generation + period)) {
+                (Some(from), Some(to)) => from.hash == to.hash && self.displacement(&from, &to, topology) == displacement,
+                _ => false,
+            }
+        };
+
+        let mut start = seen;
+        while start > 0 && repeats(start - 1) {
+            start -= 1;
+        }
+
+        start
+    }
+}
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    (u128::from(a) * u128::from(b) % u128::from(modulus)) as u64
+}
+
+fn pow_mod(base: u64, exponent: u64, modulus: u64) -> u64 {
+    let (mut result, mut base, mut exponent) = (1, base % modulus, exponent);
+
+    while exponent > 0 {
+        if exponent & 1 != 0 {
+            result = mul_mod(result, base, modulus);
+        }
+
+        base = mul_mod(base, base, modulus);
+        exponent >>= 1;
+    }
+
+    result
+}
+
+/// Returns [base]^0 through [base]^([count] - 1).
+fn powers(base: u64, count: u64, modulus: u64) -> Vec<u64> {
+    std::iter::successors(Some(1), |&power| Some(mul_mod(power, base, modulus)))
+        .take(count as usize)
+        .collect()
+}
+
+/// Returns the largest prime below 2^61 that's one more than a multiple of
+/// [order], so that roots of unity of order [order] exist modulo it.
+fn prime_modulus(order: u64) -> u64 {
+    let mut multiple = ((1 << 61) - 2) / order;
+
+    while !is_prime(multiple * order + 1) {
+        multiple -= 1;
+    }
+
+    multiple * order + 1
+}
+
+/// Returns an element of multiplicative order exactly [order] modulo the prime
+/// [modulus], which must be one more than a multiple of [order].
+fn root_of_unity(order: u64, modulus: u64) -> u64 {
+    let factors = prime_factors(order);
+
+    (2..)
+        .map(|candidate| pow_mod(candidate, (modulus - 1) / order, modulus))
+        .find(|&root| factors.iter().all(|&factor| pow_mod(root, order / factor, modulus) != 1))
+        .expect("a prime modulus should have a root of unity of each order dividing modulus - 1")
+}
+
+/// Miller-Rabin, with bases that make it exact for all u64s.
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+    if n < 2 {
+        return false;
+    }
+
+    if let Some(&base) = BASES.iter().find(|&&base| n.is_multiple_of(base)) {
+        return n == base;
+    }
+
+    let (mut odd, mut twos) = (n - 1, 0);
+    while odd.is_multiple_of(2) {
+        odd /= 2;
+        twos += 1;
+    }
+
+    BASES.iter().all(|&base| {
+        let mut x = pow_mod(base, odd, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+
+        for _ in 1..twos {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+
+        false
+    })
+}
+
+/// Returns the distinct prime factors of [n].
+fn prime_factors(mut n: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+
+    let mut factor = 2;
+    while factor * factor <= n {
+        if n.is_multiple_of(factor) {
+            factors.push(factor);
+
+            while n.is_multiple_of(factor) {
+                n /= factor;
+            }
+        }
+
+        factor += 1;
+    }
+
+    if n > 1 {
+        factors.push(n);
+    }
+
+    factors
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Sets the number of past generations compared against each new one to
+    /// detect still lifes, oscillators and spaceships. Periods up to [window]
+    /// are found. 0 (the default) disables detection.
+    pub fn set_cycle_window(&mut self, window: u32) {
+        let was_enabled = self.cycles.is_enabled();
+        self.cycles.set_window(window);
+
+        if self.cycles.is_enabled() && !was_enabled {
+            self.rebuild_cycles();
+        }
+    }
+
+    pub fn cycle_window(&self) -> u32 {
+        self.cycles.window()
+    }
+
+    /// Returns whether the current generation repeats one within the cycle
+    /// window, possibly moved. Edits, and changes to the rule or topology,
+    /// restart detection.
+    ///
+    /// Patterns are compared relative to their bounding boxes. On surfaces
+    /// with twisted edges, a spaceship isn't recognized while crossing them.
+    pub fn cycle_status(&self) -> CycleStatus {
+        self.cycles.status()
+    }
+
+    /// Returns the period of the current cycle: 1 for a still life, or 0 if
+    /// the generations aren't repeating.
+    pub fn cycle_period(&self) -> u64 {
+        self.cycles.cycle().map_or(0, |cycle| cycle.period)
+    }
+
+    /// Returns the first generation of the current cycle, as far back as the
+    /// cycle window allows, or None if the generations aren't repeating.
+    pub fn cycle_start(&self) -> Option<u64> {
+        self.cycles.cycle().map(|cycle| cycle.start)
+    }
+
+    /// Returns how far the pattern moves right each period of the current
+    /// cycle. 0 unless it's a spaceship.
+    pub fn cycle_dx(&self) -> i64 {
+        self.cycles.cycle().map_or(0, |cycle| cycle.dx)
+    }
+
+    /// Returns how far the pattern moves down each period of the current
+    /// cycle. 0 unless it's a spaceship.
+    pub fn cycle_dy(&self) -> i64 {
+        self.cycles.cycle().map_or(0, |cycle| cycle.dy)
+    }
+
+    /// Returns the speed of the current spaceship, such as "c/4 diagonal",
+    /// "c/2 orthogonal" or "(2,1)c/6", or None if this isn't a spaceship.
+    pub fn cycle_speed(&self) -> Option<String> {
+        match self.cycles.status() {
+            CycleStatus::Spaceship => self.cycles.cycle().map(|cycle| cycle.speed()),
+            _ => None,
+        }
+    }
+}
+
+// Private impl
+impl Universe {
+    /// Recomputes the cycle detector's shape from all cells, for when they
+    /// were replaced wholesale.
+    pub(crate) fn rebuild_cycles(&mut self) {
+        let (width, height, cells) = (self.width, self.height, &self.cells);
+        let states = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y, cells.get_state(x, y, width))))
+            .filter(|&(_, _, state)| state != Cell::Dead as u8);
+
+        self.cycles.rebuild(width, height, states);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CycleStatus;
+    use crate::testing::states;
+    use crate::{Cell, Universe};
+
+    #[test]
+    fn detects_blinker() {
+        let mut universe = Universe::empty(20, 20);
+        universe.set_cycle_window(100);
+        universe.load_rle("x = 3, y = 1\n3o!", 5, 5).unwrap();
+
+        universe.tick();
+        assert_eq!(universe.cycle_status(), CycleStatus::Evolving);
+        universe.tick();
+        assert_eq!(universe.cycle_status(), CycleStatus::Oscillating);
+        assert_eq!(universe.cycle_period(), 2);
+        assert_eq!(universe.cycle_start(), Some(0));
+    }
+
+    #[test]
+    fn detects_glider() {
+        let mut universe = Universe::empty(20, 20);
+        universe.set_cycle_window(100);
+        universe.load_rle("x = 3, y = 3\nbo$2bo$3o!", 0, 0).unwrap();
+
+        for _ in 0..4 {
+            universe.tick();
+        }
+
+        assert_eq!(universe.cycle_status(), CycleStatus::Spaceship);
+        assert_eq!(universe.cycle_speed().as_deref(), Some("c/4 diagonal"));
+    }
+
+    #[test]
+    fn tells_dying_cells_apart() {
+        // A block, and a cell that dies over the next two generations.
+        let mut universe = Universe::empty(20, 20);
+        universe.set_rule("B3/S23/C3").unwrap();
+        universe.set_cycle_window(100);
+        universe.load_rle("x = 2, y = 2\n2o$2o!", 2, 2).unwrap();
+        universe.set_cell_at(12, 12, Cell::Alive);
+
+        universe.tick();
+        universe.tick();
+        assert_eq!(universe.cycle_status(), CycleStatus::Evolving);
+
+        universe.tick();
+        assert_eq!(universe.cycle_status(), CycleStatus::Stable);
+        assert_eq!(universe.cycle_start(), Some(2));
+    }
+
+    #[test]
+    fn detects_generations_oscillator() {
+        let mut universe = Universe::empty(48, 48);
+        universe.set_rule("B3/S23/C4").unwrap();
+        universe.set_cycle_window(1024);
+        universe.randomize_region(16, 16, 16, 16, 0.5, 3).unwrap();
+
+        let mut generations = vec![states(&universe)];
+        while universe.cycle_status() == CycleStatus::Evolving {
+            assert!(universe.generation() < 5000, "the soup should settle");
+
+            universe.tick();
+            generations.push(states(&universe));
+        }
+
+        let (period, start) = (universe.cycle_period() as usize, universe.cycle_start().unwrap() as usize);
+        let current = generations.len() - 1;
+
+        assert_eq!(generations[current], generations[current - period]);
+        for shorter in 1..period {
+            assert_ne!(generations[current], generations[current - shorter]);
+        }
+
+        assert_eq!(generations[start], generations[start + period]);
+        if start > 0 {
+            assert_ne!(generations[start - 1], generations[start - 1 + period]);
+        }
+    }
+}
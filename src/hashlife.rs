@@ -0,0 +1,629 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Rule, Topology, Universe};
+
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// A cell beyond the edges of the universe, which is always dead and never
+/// counts as a live neighbor.
+const WALL: NodeId = 2;
+
+/// Smallest level the root is kept at.
+const MIN_ROOT_LEVEL: u32 = 3;
+
+/// Largest k accepted by [HashLife::step_pow2]. Keeps coordinates well within an i64.
+const MAX_STEP_POW2: u32 = 48;
+
+/// Garbage is collected after a step once the arena holds this many nodes.
+const MAX_NODES: usize = 1 << 22;
+
+/// A node of the quadtree: a single cell (level 0) or a 2^level x 2^level
+/// square made of four level - 1 children.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    level: u32,
+
+    /// The nw, ne, sw and se quadrants. Unused for leaves.
+    children: [NodeId; 4],
+
+    /// The number of live cells, or u64::MAX if there are more.
+    population: u64,
+}
+
+/// A HashLife engine: the cells of a universe, walled in by cells that are
+/// always dead, stored as a canonicalised quadtree that can advance very large
+/// numbers of generations at once by memoizing the future of each node.
+#[wasm_bindgen]
+pub struct HashLife {
+    /// All nodes, indexed by NodeId. Identical subtrees share a single node.
+    nodes: Vec<Node>,
+    canonical: HashMap<[NodeId; 4], NodeId>,
+
+    /// Maps (node, j) to the center of node, 2^j generations later.
+    successors: HashMap<(NodeId, u32), NodeId>,
+
+    /// walls[level] is the node of that level made only of walls.
+    walls: Vec<NodeId>,
+
+    root: NodeId,
+
+    /// Plane coordinates of the top-left corner of [root].
+    origin_x: i64,
+    origin_y: i64,
+
+    generation: u64,
+    rule: Rule,
+
+    /// The size and topology of the universe this was created from. Wrapping
+    /// topologies repeat its cells across the plane (see [build]).
+    width: u32,
+    height: u32,
+    topology: Topology,
+}
+
+#[wasm_bindgen]
+impl HashLife {
+    /// Creates an engine with [universe]'s cells, rule and generation.
+    /// [universe]'s top-left cell is placed at (0, 0) on the plane, so results
+    /// match [Universe::tick]: on Topology::Plane cells beyond its edges are
+    /// walls, while on Topology::Torus and Topology::Cylinder its cells are
+    /// repeated across the edges that wrap.
+    ///
+    /// Topology::KleinBottle and Topology::CrossSurface aren't supported, since
+    /// they mirror cells as they wrap, which only matches [Universe::tick] for
+    /// rules that are symmetric.
+    pub fn from_universe(universe: &Universe) -> Result<HashLife, String> {
+        if ![Topology::Plane, Topology::Torus, Topology::Cylinder].contains(&universe.topology) {
+            return Err(format!("HashLife doesn't support the {:?} topology, which mirrors cells as they wrap", universe.topology));
+        }
+
+        if universe.rule.has_b0() {
+            return Err(format!("HashLife doesn't support B0 rules, such as {}", universe.rule));
+        }
+
+        if let Some(family) = universe.rule.family() {
+            return Err(format!("HashLife doesn't support {} rules, such as {}", family, universe.rule));
+        }
+
+        let mut hashlife = HashLife::new(universe.rule);
+        hashlife.generation = universe.generation;
+        hashlife.width = universe.width;
+        hashlife.height = universe.height;
+
+        // An empty universe has nothing to repeat.
+        if universe.width > 0 && universe.height > 0 {
+            hashlife.topology = universe.topology;
+        }
+
+        let cells: Vec<NodeId> = (0..universe.height)
+            .flat_map(|y| (0..universe.width).map(move |x| (x, y)))
+            .map(|(x, y)| match universe.get_cell_at(x, y) {
+                Cell::Alive => ALIVE,
+                Cell::Dead => DEAD,
+            })
+            .collect();
+
+        hashlife.root = hashlife.build(&cells, 0, 0, hashlife.fitting_level(), Topology::Plane, &mut HashMap::new());
+        Ok(hashlife)
+    }
+
+    /// Advances this by 2^[k] generations.
+    pub fn step_pow2(&mut self, k: u32) -> Result<(), String> {
+        if k > MAX_STEP_POW2 {
+            return Err(format!("Can't step by 2^{} generations at once (the maximum is 2^{})", k, MAX_STEP_POW2));
+        }
+
+        if self.topology == Topology::Plane {
+            // The successor of the root is its center, 2^k generations later. For nothing
+            // to be lost, the root must be large enough to step by 2^k, and the pattern
+            // must stay within the center after spreading by up to 2^k cells.
+            while self.nodes[self.root as usize].level < k + 2 || !self.is_padded(self.root) {
+                self.expand();
+            }
+            self.expand();
+
+            let quarter = 1i64 << (self.nodes[self.root as usize].level - 2);
+            self.root = self.successor(self.root, k);
+            self.origin_x += quarter;
+            self.origin_y += quarter;
+        } else {
+            self.step_wrapped(k);
+        }
+
+        self.generation += 1 << k;
+
+        if self.nodes.len() > MAX_NODES {
+            self.collect_garbage();
+        }
+
+        Ok(())
+    }
+
+    /// Advances this by [generations] generations.
+    pub fn step(&mut self, generations: u64) -> Result<(), String> {
+        for k in 0..64 {
+            if generations & (1 << k) != 0 {
+                self.step_pow2(k)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Clears [universe] and copies the cells of this into it, such that
+    /// ([left], [top]) on the plane lands on [universe]'s top-left cell. The
+    /// universe takes on the generation of this.
+    pub fn project_into(&self, universe: &mut Universe, left: i32, top: i32) {
+        universe.reset_cells();
+        self.project_node(self.root, self.origin_x - i64::from(left), self.origin_y - i64::from(top), universe);
+
+        universe.generation = self.generation;
+        universe.stats.record_jump();
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root as usize].population
+    }
+
+    /// Returns the number of nodes in the quadtree cache.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Drops all nodes and memoized results not reachable from the current root.
+    pub fn collect_garbage(&mut self) {
+        let mut collected = HashLife::new(self.rule);
+        let mut remap = HashMap::new();
+
+        collected.root = collected.copy_from(self, self.root, &mut remap);
+        collected.origin_x = self.origin_x;
+        collected.origin_y = self.origin_y;
+        collected.generation = self.generation;
+        collected.width = self.width;
+        collected.height = self.height;
+        collected.topology = self.topology;
+
+        *self = collected;
+    }
+}
+
+// Private impl
+impl HashLife {
+    fn new(rule: Rule) -> HashLife {
+        let leaf = |population| Node { level: 0, children: [DEAD; 4], population };
+
+        HashLife {
+            nodes: vec![leaf(0), leaf(1), leaf(0)],
+            canonical: HashMap::new(),
+            successors: HashMap::new(),
+            walls: vec![WALL],
+
+            root: DEAD,
+            origin_x: 0,
+            origin_y: 0,
+
+            generation: 0,
+            rule,
+
+            width: 0,
+            height: 0,
+            topology: Topology::Plane,
+        }
+    }
+
+    /// Returns the smallest level of a root that holds the whole universe.
+    fn fitting_level(&self) -> u32 {
+        let mut level = MIN_ROOT_LEVEL;
+        while (1u64 << level) < u64::from(self.width.max(self.height)) {
+            level += 1;
+        }
+
+        level
+    }
+
+    /// Advances this by 2^[k] generations on a wrapping topology. The universe
+    /// is repeated across a root large enough that no cell more than 2^k cells
+    /// outside of it can reach its center, and one copy of the universe is
+    /// then cut back out of the center.
+    fn step_wrapped(&mut self, k: u32) {
+        let cells = self.cells(self.root);
+        let level = (k + 2).max(self.fitting_level() + 1);
+        let quarter = 1i64 << (level - 2);
+
+        let repeated = self.build(&cells, -quarter, -quarter, level, self.topology, &mut HashMap::new());
+        let stepped = self.successor(repeated, k);
+
+        let cells = self.cells(stepped);
+        self.root = self.build(&cells, 0, 0, self.fitting_level(), Topology::Plane, &mut HashMap::new());
+    }
+
+    /// Returns the canonical node with the given quadrants.
+    fn join(&mut self, children: [NodeId; 4]) -> NodeId {
+        if let Some(&id) = self.canonical.get(&children) {
+            return id;
+        }
+
+        let node = Node {
+            level: self.nodes[children[0] as usize].level + 1,
+            children,
+            // Repeating a universe across a large root can outgrow a u64.
+            population: children.iter().fold(0, |population: u64, &child| population.saturating_add(self.nodes[child as usize].population)),
+        };
+
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(node);
+        self.canonical.insert(children, id);
+
+        id
+    }
+
+    fn wall(&mut self, level: u32) -> NodeId {
+        while self.walls.len() <= level as usize {
+            let smaller = *self.walls.last().unwrap();
+            let node = self.join([smaller; 4]);
+            self.walls.push(node);
+        }
+
+        self.walls[level as usize]
+    }
+
+    fn children(&self, id: NodeId) -> [NodeId; 4] {
+        self.nodes[id as usize].children
+    }
+
+    /// Returns the center of [id], half its size.
+    fn center(&mut self, id: NodeId) -> NodeId {
+        let [nw, ne, sw, se] = self.children(id);
+        let center = [self.children(nw)[3], self.children(ne)[2], self.children(sw)[1], self.children(se)[0]];
+
+        self.join(center)
+    }
+
+    /// Returns true iff all live cells in [id] are within its center.
+    fn is_padded(&self, id: NodeId) -> bool {
+        let [nw, ne, sw, se] = self.children(id);
+        let center_population: u64 = [(nw, 3), (ne, 2), (sw, 1), (se, 0)].iter()
+            .map(|&(quadrant, inner)| self.nodes[self.children(quadrant)[inner] as usize].population)
+            .sum();
+
+        center_population == self.nodes[id as usize].population
+    }
+
+    /// Surrounds the root with walls, doubling its size.
+    fn expand(&mut self) {
+        let level = self.nodes[self.root as usize].level;
+        let wall = self.wall(level - 1);
+        let [nw, ne, sw, se] = self.children(self.root);
+
+        let nw = self.join([wall, wall, wall, nw]);
+        let ne = self.join([wall, wall, ne, wall]);
+        let sw = self.join([wall, sw, wall, wall]);
+        let se = self.join([se, wall, wall, wall]);
+
+        self.root = self.join([nw, ne, sw, se]);
+
+        let half = 1i64 << (level - 1);
+        self.origin_x -= half;
+        self.origin_y -= half;
+    }
+
+    /// Returns the center of [id] (of level k), 2^min([j], k - 2) generations later.
+    fn successor(&mut self, id: NodeId, j: u32) -> NodeId {
+        let level = self.nodes[id as usize].level;
+        let j = j.min(level - 2);
+
+        // Without live cells nothing changes, since B0 rules aren't supported.
+        if self.nodes[id as usize].population == 0 {
+            return self.center(id);
+        }
+
+        if let Some(&result) = self.successors.get(&(id, j)) {
+            return result;
+        }
+
+        let result = if level == 2 {
+            self.evolve_4x4(id)
+        } else {
+            let [a, b, c, d] = self.children(id);
+            let [aa, ab, ac, ad] = self.children(a);
+            let [ba, bb, bc, bd] = self.children(b);
+            let [ca, cb, cc, cd] = self.children(c);
+            let [da, db, dc, dd] = self.children(d);
+
+            // The nine overlapping level - 1 squares tiling [id], advanced by 2^j generations
+            // (or 2^(level - 3), if j is as large as possible).
+            let squares = [
+                [aa, ab, ac, ad], [ab, ba, ad, bc], [ba, bb, bc, bd],
+                [ac, ad, ca, cb], [ad, bc, cb, da], [bc, bd, da, db],
+                [ca, cb, cc, cd], [cb, da, cd, dc], [da, db, dc, dd],
+            ];
+
+            let mut c = [DEAD; 9];
+            for (i, square) in squares.iter().enumerate() {
+                let square = self.join(*square);
+                c[i] = self.successor(square, j);
+            }
+
+            let quads = [
+                [c[0], c[1], c[3], c[4]], [c[1], c[2], c[4], c[5]],
+                [c[3], c[4], c[6], c[7]], [c[4], c[5], c[7], c[8]],
+            ];
+
+            let mut result = [DEAD; 4];
+            for (i, quad) in quads.iter().enumerate() {
+                result[i] = if j < level - 2 {
+                    // Already far enough: take the center of each quad without stepping.
+                    let [nw, ne, sw, se] = *quad;
+                    let center = [self.children(nw)[3], self.children(ne)[2], self.children(sw)[1], self.children(se)[0]];
+                    self.join(center)
+                } else {
+                    let quad = self.join(*quad);
+                    self.successor(quad, j)
+                };
+            }
+
+            self.join(result)
+        };
+
+        self.successors.insert((id, j), result);
+        result
+    }
+
+    /// Applies the rule once to the center 2x2 cells of the level 2 node [id].
+    fn evolve_4x4(&mut self, id: NodeId) -> NodeId {
+        let leaf = |x: i32, y: i32| -> NodeId {
+            let quadrant = self.children(id)[((y / 2) * 2 + x / 2) as usize];
+            self.children(quadrant)[((y % 2) * 2 + x % 2) as usize]
+        };
+
+        let mut next = [DEAD; 4];
+        for (i, &(x, y)) in [(1, 1), (2, 1), (1, 2), (2, 2)].iter().enumerate() {
+            // Walls stay walls, and count as dead neighbors.
+            if leaf(x, y) == WALL {
+                next[i] = WALL;
+                continue;
+            }
+
+            let mut neighborhood = 0;
+
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if leaf(x + dx, y + dy) == ALIVE {
+                        neighborhood |= 1 << (3 * (dy + 1) + (dx + 1));
+                    }
+                }
+            }
+
+            next[i] = match self.rule.next_state(neighborhood) {
+                Cell::Alive => ALIVE,
+                Cell::Dead => DEAD,
+            };
+        }
+
+        self.join(next)
+    }
+
+    /// Builds the level [level] node whose top-left cell is ([x], [y]) on the
+    /// plane, where the row-major [cells] of the universe are repeated across
+    /// the edges that [topology] wraps, and cells beyond the others are walls.
+    /// [built] memoizes nodes by where they start within the universe, so
+    /// that each repeat is only built once.
+    fn build(&mut self, cells: &[NodeId], x: i64, y: i64, level: u32, topology: Topology, built: &mut HashMap<(i64, i64, u32), NodeId>) -> NodeId {
+        let (width, height) = (i64::from(self.width), i64::from(self.height));
+        let (wraps_x, wraps_y) = topology.wraps();
+        let size = 1i64 << level;
+
+        let beyond = |start: i64, length: i64, wraps: bool| !wraps && (start >= length || start + size <= 0);
+        if beyond(x, width, wraps_x) || beyond(y, height, wraps_y) {
+            return self.wall(level);
+        }
+
+        if level == 0 {
+            let (x, y) = topology.resolve(x, y, self.width, self.height).unwrap();
+            return cells[y as usize * self.width as usize + x as usize];
+        }
+
+        let key = (
+            if wraps_x { x.rem_euclid(width) } else { x },
+            if wraps_y { y.rem_euclid(height) } else { y },
+            level,
+        );
+
+        if let Some(&id) = built.get(&key) {
+            return id;
+        }
+
+        let half = size / 2;
+        let children = [
+            self.build(cells, x, y, level - 1, topology, built),
+            self.build(cells, x + half, y, level - 1, topology, built),
+            self.build(cells, x, y + half, level - 1, topology, built),
+            self.build(cells, x + half, y + half, level - 1, topology, built),
+        ];
+
+        let id = self.join(children);
+        built.insert(key, id);
+
+        id
+    }
+
+    /// Returns the row-major leaves of the universe, for the node [id] whose
+    /// top-left cell is the universe's.
+    fn cells(&self, id: NodeId) -> Vec<NodeId> {
+        let leaf = |x: u32, y: u32| {
+            let (x, y) = (u64::from(x), u64::from(y));
+            let mut id = id;
+
+            for level in (0..self.nodes[id as usize].level).rev() {
+                let quadrant = ((y >> level) & 1) * 2 + ((x >> level) & 1);
+                id = self.children(id)[quadrant as usize];
+            }
+
+            id
+        };
+
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| leaf(x, y))
+            .collect()
+    }
+
+    /// Sets the live cells of [id] in [universe], where ([x], [y]) is the
+    /// position of [id]'s top-left corner relative to [universe]'s.
+    fn project_node(&self, id: NodeId, x: i64, y: i64, universe: &mut Universe) {
+        let node = self.nodes[id as usize];
+        let size = 1i64 << node.level;
+
+        let outside = x >= i64::from(universe.width) || y >= i64::from(universe.height) || x + size <= 0 || y + size <= 0;
+        if node.population == 0 || outside {
+            return;
+        }
+
+        if node.level == 0 {
+            universe.write_cell(x as u32, y as u32, Cell::Alive);
+            return;
+        }
+
+        let half = size / 2;
+        let [nw, ne, sw, se] = node.children;
+
+        self.project_node(nw, x, y, universe);
+        self.project_node(ne, x + half, y, universe);
+        self.project_node(sw, x, y + half, universe);
+        self.project_node(se, x + half, y + half, universe);
+    }
+
+    /// Copies the subtree [id] of [other] into this.
+    fn copy_from(&mut self, other: &HashLife, id: NodeId, remap: &mut HashMap<NodeId, NodeId>) -> NodeId {
+        if [DEAD, ALIVE, WALL].contains(&id) {
+            return id;
+        }
+
+        if let Some(&copied) = remap.get(&id) {
+            return copied;
+        }
+
+        let mut children = other.children(id);
+        for child in children.iter_mut() {
+            *child = self.copy_from(other, *child, remap);
+        }
+
+        let copied = self.join(children);
+        remap.insert(id, copied);
+
+        copied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HashLife;
+    use crate::testing::states;
+    use crate::{Topology, Universe};
+
+    /// Checks that 2^[k] generations of HashLife match as many ticks of [universe].
+    fn assert_matches_tick(mut universe: Universe, k: u32) {
+        let mut hashlife = HashLife::from_universe(&universe).unwrap();
+        hashlife.step_pow2(k).unwrap();
+
+        for _ in 0..1 << k {
+            universe.tick();
+        }
+
+        let mut projected = Universe::empty(universe.width(), universe.height());
+        hashlife.project_into(&mut projected, 0, 0);
+
+        assert_eq!(states(&projected), states(&universe));
+        assert_eq!(projected.generation(), universe.generation());
+        assert_eq!(projected.population(), universe.population());
+    }
+
+    fn plane_universe(width: u32, height: u32, rle: &str, rule: &str) -> Universe {
+        let mut universe = Universe::empty(width, height);
+        universe.set_topology(Topology::Plane);
+        universe.set_rule(rule).unwrap();
+        universe.load_rle(rle, width / 3, height / 3).unwrap();
+
+        universe
+    }
+
+    #[test]
+    fn matches_tick_within_edges() {
+        for k in 0..6 {
+            assert_matches_tick(plane_universe(64, 64, "x = 3, y = 3\nbo$2bo$3o!", "B3/S23"), k);
+        }
+    }
+
+    #[test]
+    fn matches_tick_at_walls() {
+        let r_pentomino = "x = 3, y = 3\nb2o$2o$bo!";
+
+        for k in 0..9 {
+            assert_matches_tick(plane_universe(40, 27, r_pentomino, "B3/S23"), k);
+            assert_matches_tick(plane_universe(33, 50, r_pentomino, "B36/S23"), k);
+            assert_matches_tick(plane_universe(30, 30, r_pentomino, "B2-a/S12"), k);
+        }
+    }
+
+    #[test]
+    fn continues_from_generation() {
+        let mut universe = plane_universe(40, 40, "x = 3, y = 3\nb2o$2o$bo!", "B3/S23");
+        for _ in 0..5 {
+            universe.tick();
+        }
+
+        assert_matches_tick(universe, 4);
+    }
+
+    #[test]
+    fn matches_tick_across_wrapping_edges() {
+        let r_pentomino = "x = 3, y = 3\nb2o$2o$bo!";
+
+        for topology in [Topology::Torus, Topology::Cylinder] {
+            for k in 0..8 {
+                for (width, height, rule) in [(23, 17, "B3/S23"), (16, 16, "B36/S23"), (9, 30, "B2-a/S12")] {
+                    let mut universe = plane_universe(width, height, r_pentomino, rule);
+                    universe.set_topology(topology);
+
+                    assert_matches_tick(universe, k);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn jumps_far_on_torus() {
+        // A glider crosses a 16 x 16 torus diagonally every 64 generations.
+        let mut universe = Universe::empty(16, 16);
+        universe.load_rle("x = 3, y = 3\nbo$2bo$3o!", 14, 14).unwrap();
+
+        let mut hashlife = HashLife::from_universe(&universe).unwrap();
+        hashlife.step_pow2(40).unwrap();
+
+        let mut projected = Universe::empty(16, 16);
+        hashlife.project_into(&mut projected, 0, 0);
+
+        assert_eq!(states(&projected), states(&universe));
+        assert_eq!(hashlife.population(), 5);
+        assert_eq!(hashlife.generation(), 1 << 40);
+    }
+
+    #[test]
+    fn rejects_mirroring_topologies() {
+        let mut universe = Universe::empty(16, 16);
+
+        for topology in [Topology::KleinBottle, Topology::CrossSurface] {
+            universe.set_topology(topology);
+            assert!(HashLife::from_universe(&universe).is_err());
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use std::fmt;
+
+use crate::rule::CENTER;
+
+/// The letters naming the arrangements of each number of live neighbors (0 to
+/// 8), in canonical order. Arrangements that are rotations or reflections of
+/// each other share a letter.
+const LETTERS: [&str; 9] = ["", "ce", "cekain", "cekainyqjr", "cekainyqjrtwz", "cekainyqjr", "cekain", "ce", ""];
+
+/// Offsets of the neighbors of a cell, clockwise from north. Arrangements are
+/// written as masks where bit i is set iff the neighbor at NEIGHBORS[i] is alive.
+const NEIGHBORS: [(u32, u32); 8] = [(1, 0), (2, 0), (2, 1), (2, 2), (1, 2), (0, 2), (0, 1), (0, 0)];
+
+/// An arrangement of each letter of counts 1 to 4, as a mask of [NEIGHBORS].
+/// The arrangements of 5 to 7 neighbors are those of 3 to 1 neighbors with
+/// live and dead swapped.
+const ARRANGEMENTS: [&[u8]; 5] = [
+    &[],
+    // c: NE; e: N.
+    &[0b10, 0b1],
+    // c: NE, SE; e: N, E; k: N, SE; a: N, NE; i: N, S; n: NE, SW.
+    &[0b1010, 0b101, 0b1001, 0b11, 0b1_0001, 0b10_0010],
+    // c: NE, SE, SW; e: N, E, S; k: N, E, SW; a: N, NE, E; i: N, NE, NW;
+    // n: N, NE, SE; y: N, SE, SW; q: N, NE, SW; j: N, NE, W; r: N, NE, S.
+    &[0b10_1010, 0b1_0101, 0b10_0101, 0b111, 0b1000_0011, 0b1011, 0b10_1001, 0b10_0011, 0b100_0011, 0b1_0011],
+    // c: NE, SE, SW, NW; e: N, E, S, W; k: N, NE, SE, W; a: N, NE, E, SE;
+    // i: N, NE, SE, S; n: N, NE, SE, NW; y: N, NE, SE, SW; q: N, NE, E, SW;
+    // j: N, NE, S, W; r: N, NE, E, S; t: N, NE, S, NW; w: N, NE, SW, W;
+    // z: N, NE, S, SW.
+    &[
+        0b1010_1010, 0b101_0101, 0b100_1011, 0b1111, 0b1_1011, 0b1000_1011, 0b10_1011,
+        0b10_0111, 0b101_0011, 0b1_0111, 0b1001_0011, 0b110_0011, 0b11_0011,
+    ],
+];
+
+/// The live neighbor arrangements of an isotropic non-totalistic rule, written
+/// in Hensel notation: each neighbor count may be followed by letters that
+/// limit it to some arrangements, or by '-' and the letters to exclude (e.g.
+/// "B2-a/S12").
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Isotropic {
+    /// Bit i of birth[n] is set iff a dead cell with n live neighbors in the
+    /// arrangement named by letter i of LETTERS[n] comes alive.
+    pub birth: [u16; 9],
+
+    /// As [birth], but for live cells that stay alive.
+    pub survival: [u16; 9],
+}
+
+impl Isotropic {
+    /// Parses a B or S section such as "2-a" or "34q", returning the letters of
+    /// each count as in [Isotropic::birth]. Errors are reasons, without the
+    /// rulestring.
+    pub fn parse_section(section: &str) -> Result<[u16; 9], String> {
+        let mut letters = [0; 9];
+        let mut chars = section.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let count = match c.to_digit(10) {
+                Some(count) if count <= 8 => count as usize,
+                Some(count) => return Err(format!("a cell has at most 8 neighbors, but {} was given", count)),
+                None => return Err(format!("{:?} is not preceded by a neighbor count", c)),
+            };
+
+            let exclude = chars.next_if_eq(&'-').is_some();
+            let mut mask = 0;
+
+            while let Some(letter) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+                let idx = LETTERS[count].find(letter.to_ascii_lowercase())
+                    .ok_or_else(|| format!("{:?} doesn't name an arrangement of {} neighbors", letter, count))?;
+                mask |= 1 << idx;
+            }
+
+            if exclude && mask == 0 {
+                return Err(format!("expected letters to exclude after \"{}-\"", count));
+            }
+
+            let all = Self::all_letters(count);
+            letters[count] |= match (exclude, mask) {
+                (true, _) => all & !mask,
+                (false, 0) => all,
+                (false, _) => mask,
+            };
+        }
+
+        Ok(letters)
+    }
+
+    /// Returns the mask of all letters of [count], which stands for every
+    /// arrangement of that many neighbors, even for counts without letters.
+    pub fn all_letters(count: usize) -> u16 {
+        (1 << LETTERS[count].len().max(1)) - 1
+    }
+
+    /// Returns the mask of the counts that have all of their [letters], unless
+    /// some count has only some of them.
+    pub fn counts(letters: &[u16; 9]) -> Option<u16> {
+        letters.iter().enumerate().try_fold(0, |counts, (count, &letters)| match letters {
+            0 => Some(counts),
+            letters if letters == Self::all_letters(count) => Some(counts | 1 << count),
+            _ => None,
+        })
+    }
+
+    /// Returns the 512-entry lookup table of this, as described by
+    /// [Rule::next_cell_state].
+    pub fn table(&self) -> [u64; 8] {
+        let letter_of = letter_table();
+        let mut table = [0; 8];
+
+        for neighborhood in 0..512_usize {
+            let arrangement = arrangement_of(neighborhood as u16);
+            let count = arrangement.count_ones() as usize;
+            let letters = if neighborhood as u16 & CENTER != 0 { self.survival[count] } else { self.birth[count] };
+
+            if letters & (1 << letter_of[usize::from(arrangement)]) != 0 {
+                table[neighborhood / 64] |= 1 << (neighborhood % 64);
+            }
+        }
+
+        table
+    }
+
+    /// Returns the letters of the rule with the lookup [table], unless it
+    /// isn't isotropic.
+    pub fn from_table(table: &[u64; 8]) -> Option<Isotropic> {
+        let letter_of = letter_table();
+        let mut isotropic = Isotropic { birth: [0; 9], survival: [0; 9] };
+
+        for neighborhood in (0..512_usize).filter(|&neighborhood| table[neighborhood / 64] & (1 << (neighborhood % 64)) != 0) {
+            let arrangement = arrangement_of(neighborhood as u16);
+            let letters = if neighborhood as u16 & CENTER != 0 { &mut isotropic.survival } else { &mut isotropic.birth };
+
+            letters[arrangement.count_ones() as usize] |= 1 << letter_of[usize::from(arrangement)];
+        }
+
+        // Each letter stands for all of its arrangements, so the letters only
+        // describe [table] if it doesn't separate any of them.
+        if isotropic.table() == *table {
+            Some(isotropic)
+        } else {
+            None
+        }
+    }
+
+    /// Formats the letters of a B or S section, like "2-a3".
+    pub fn fmt_section(letters: &[u16; 9], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (count, &mask) in letters.iter().enumerate() {
+            if mask == 0 {
+                continue;
+            }
+
+            write!(f, "{}", count)?;
+
+            let all = Self::all_letters(count);
+            if mask == all {
+                continue;
+            }
+
+            // Whichever of the included or excluded letters is shorter.
+            let (prefix, shown) = if mask.count_ones() > (all & !mask).count_ones() { ("-", all & !mask) } else { ("", mask) };
+
+            write!(f, "{}", prefix)?;
+            for (idx, letter) in LETTERS[count].chars().enumerate() {
+                if shown & (1 << idx) != 0 {
+                    write!(f, "{}", letter)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the mask of [NEIGHBORS] that are alive in the 3x3 [neighborhood].
+fn arrangement_of(neighborhood: u16) -> u8 {
+    NEIGHBORS.iter().enumerate()
+        .filter(|&(_, &(x, y))| neighborhood & (1 << (y * 3 + x)) != 0)
+        .fold(0, |arrangement, (idx, _)| arrangement | 1 << idx)
+}
+
+/// Maps each arrangement of neighbors to the index of its letter in LETTERS.
+fn letter_table() -> [u8; 256] {
+    // Rotating by 90 degrees moves each neighbor two places clockwise, and
+    // reflecting left to right swaps the neighbor at i with that at 8 - i.
+    let rotate = |arrangement: u8| arrangement.rotate_left(2);
+    let reflect = |arrangement: u8| (0..8).filter(|i| arrangement & (1 << i) != 0).fold(0_u8, |reflected, i| reflected | 1 << ((8 - i) % 8));
+
+    let mut letter_of = [0; 256];
+
+    for count in 1..8 {
+        let (representatives, invert) = if count <= 4 { (ARRANGEMENTS[count], false) } else { (ARRANGEMENTS[8 - count], true) };
+
+        for (letter, &representative) in representatives.iter().enumerate() {
+            let mut arrangement = if invert { !representative } else { representative };
+
+            for _ in 0..4 {
+                letter_of[usize::from(arrangement)] = letter as u8;
+                letter_of[usize::from(reflect(arrangement))] = letter as u8;
+                arrangement = rotate(arrangement);
+            }
+        }
+    }
+
+    letter_of
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Isotropic, LETTERS};
+    use crate::rule::CENTER;
+    use crate::testing::live_cells;
+    use crate::{Rule, Universe};
+
+    /// Returns the isotropic rule in which only dead cells with [count] live
+    /// neighbors in the arrangement of [letter] come alive.
+    fn single_letter(count: usize, letter: usize) -> Isotropic {
+        let mut isotropic = Isotropic { birth: [0; 9], survival: [0; 9] };
+        isotropic.birth[count] = 1 << letter;
+
+        isotropic
+    }
+
+    #[test]
+    fn round_trips_every_letter() {
+        for (count, letters) in LETTERS.iter().enumerate() {
+            for letter in letters.chars() {
+                let rulestring = format!("B{}{}/S{}{}", count, letter, count, letter);
+                assert_eq!(Rule::parse(&rulestring).unwrap().to_string(), rulestring);
+            }
+        }
+
+        for rulestring in &["B3/S2-i34q", "B2-a/S12", "B2ce3-ai/S1e23-q4t", "B34-w5y/S2-c36k8"] {
+            assert_eq!(Rule::parse(rulestring).unwrap().to_string(), *rulestring);
+        }
+    }
+
+    #[test]
+    fn letters_partition_arrangements() {
+        for (count, letters) in LETTERS.iter().enumerate().filter(|(_, letters)| !letters.is_empty()) {
+            let mut arrangements = 0;
+
+            for letter in 0..letters.len() {
+                let isotropic = single_letter(count, letter);
+                let table = isotropic.table();
+                let size: u32 = table.iter().map(|word| word.count_ones()).sum();
+
+                assert!(size > 0, "{}{} has no arrangements", count, &letters[letter..=letter]);
+                assert_eq!(Isotropic::from_table(&table), Some(isotropic));
+
+                let survival = Isotropic { birth: [0; 9], survival: isotropic.birth };
+                assert_eq!(Isotropic::from_table(&survival.table()), Some(survival));
+
+                arrangements += size;
+            }
+
+            let neighborhoods = (0..512_u16).filter(|n| n & CENTER == 0 && n.count_ones() as usize == count).count();
+            assert_eq!(arrangements as usize, neighborhoods, "count {}", count);
+        }
+    }
+
+    #[test]
+    fn letters_name_their_shapes() {
+        // Neighborhoods as rows of 3x3 cells, top first.
+        let shapes = [
+            ("2i", ["..." , "o.o", "..."]),
+            ("2n", ["..o", "...", "o.."]),
+            ("3i", ["ooo", "...", "..."]),
+            ("3y", ["o.o", "...", ".o."]),
+            ("4t", ["ooo", "...", ".o."]),
+            ("4w", ["o..", "o..", ".oo"]),
+            ("4z", ["oo.", "...", ".oo"]),
+            ("4c", ["o.o", "...", "o.o"]),
+            ("5e", ["o.o", "...", "ooo"]),
+        ];
+
+        for (letter, rows) in &shapes {
+            let neighborhood = rows.iter().flat_map(|row| row.chars()).enumerate()
+                .filter(|&(_, c)| c == 'o')
+                .fold(0, |neighborhood, (idx, _)| neighborhood | 1 << idx);
+
+            let rule = Rule::parse(&format!("B{}/S", letter)).unwrap();
+            assert_eq!(rule.next_cell_state(0, neighborhood), 1, "{}", letter);
+
+            let others = Rule::parse(&format!("B{}-{}/S", &letter[..1], &letter[1..])).unwrap();
+            assert_eq!(others.next_cell_state(0, neighborhood), 0, "{}", letter);
+        }
+    }
+
+    #[test]
+    fn rejects_tables_that_split_letters() {
+        let mut table = single_letter(2, 0).table();
+        let lowest = table.iter().enumerate().find(|(_, &word)| word != 0).map(|(idx, &word)| (idx, word.trailing_zeros())).unwrap();
+        table[lowest.0] &= !(1 << lowest.1);
+
+        assert_eq!(Isotropic::from_table(&table), None);
+    }
+
+    #[test]
+    fn tlife_blinker_dies() {
+        // The center of a blinker has two opposite (2i) neighbors, so it dies,
+        // while the cells beside it are born from three in a row (3i).
+        let mut universe = Universe::empty(8, 8);
+        universe.set_rule("B3/S2-i34q").unwrap();
+        universe.load_rle("x = 3, y = 1\n3o!", 2, 3).unwrap();
+
+        universe.tick();
+        assert_eq!(live_cells(&universe), vec![(3, 2), (3, 4)]);
+
+        universe.tick();
+        assert!(live_cells(&universe).is_empty());
+    }
+
+    #[test]
+    fn tlife_glider_moves() {
+        let mut universe = Universe::empty(16, 16);
+        universe.set_rule("B3/S2-i34q").unwrap();
+        universe.load_rle("x = 3, y = 3\nbo$2bo$3o!", 4, 4).unwrap();
+        let start = live_cells(&universe);
+
+        for _ in 0..4 {
+            universe.tick();
+        }
+
+        let moved: Vec<_> = start.iter().map(|&(x, y)| (x + 1, y + 1)).collect();
+        assert_eq!(live_cells(&universe), moved);
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Universe};
+
+/// Default for the memory used by generation history, in bytes.
+pub const DEFAULT_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// The cells changed by one tick, as (index, state before the tick) pairs.
+type Delta = Vec<(u32, u8)>;
+
+/// A ring buffer of the most recent generations, each stored as the delta
+/// from the generation before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct History {
+    deltas: VecDeque<Delta>,
+
+    /// Maximum number of generations remembered. 0 disables history.
+    depth: u32,
+
+    /// Memory used by [deltas], in bytes.
+    memory_used: usize,
+    memory_limit: usize,
+}
+
+impl History {
+    pub fn new(depth: u32, memory_limit: usize) -> History {
+        History {
+            deltas: VecDeque::new(),
+            depth,
+
+            memory_used: 0,
+            memory_limit,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.depth > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn push(&mut self, delta: Delta) {
+        self.memory_used += Self::size_of(&delta);
+        self.deltas.push_back(delta);
+
+        self.enforce_limits();
+    }
+
+    pub fn pop(&mut self) -> Option<Delta> {
+        let delta = self.deltas.pop_back()?;
+        self.memory_used -= Self::size_of(&delta);
+
+        Some(delta)
+    }
+
+    pub fn clear(&mut self) {
+        self.deltas.clear();
+        self.memory_used = 0;
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, depth: u32) {
+        self.depth = depth;
+        self.enforce_limits();
+    }
+
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    pub fn set_memory_limit(&mut self, memory_limit: usize) {
+        self.memory_limit = memory_limit;
+        self.enforce_limits();
+    }
+
+    /// Forgets the oldest generations until within [depth] and [memory_limit].
+    fn enforce_limits(&mut self) {
+        while self.deltas.len() > self.depth as usize || self.memory_used > self.memory_limit {
+            let oldest = self.deltas.pop_front().expect("limits should be exceeded only by remembered generations");
+            self.memory_used -= Self::size_of(&oldest);
+        }
+    }
+
+    fn size_of(delta: &Delta) -> usize {
+        size_of::<Delta>() + delta.len() * size_of::<(u32, u8)>()
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Steps back by up to [generations] generations, as far as the history
+    /// allows. Returns the number of generations stepped back.
+    pub fn step_back(&mut self, generations: u32) -> u32 {
+        for stepped in 0..generations {
+            let delta = match self.history.pop() {
+                Some(delta) => delta,
+                None => return stepped,
+            };
+
+            for (idx, state) in delta {
+                let (x, y) = (idx % self.width, idx / self.width);
+
+                let before = self.cells.get_state(x, y, self.width);
+
+                self.stats.record_edit(Cell::from_state(before), Cell::from_state(state));
+                self.cycles.record_change(x, y, before, state);
+                self.cells.set_state(x, y, self.width, state);
+                self.tiles.mark_cell(x, y);
+                self.changed_cells.record(idx);
+            }
+
+            self.cycles.reset();
+            self.journal.clear();
+
+            self.generation -= 1;
+            self.stats.record_step_back();
+        }
+
+        generations
+    }
+
+    /// Sets the number of past generations remembered for [step_back].
+    /// 0 (the default) disables history.
+    pub fn set_history_depth(&mut self, depth: u32) {
+        self.history.set_depth(depth);
+    }
+
+    pub fn history_depth(&self) -> u32 {
+        self.history.depth()
+    }
+
+    /// Sets the maximum memory, in bytes, used to remember past generations.
+    /// The oldest generations are forgotten first.
+    pub fn set_history_memory_limit(&mut self, bytes: usize) {
+        self.history.set_memory_limit(bytes);
+    }
+
+    pub fn history_memory_limit(&self) -> usize {
+        self.history.memory_limit()
+    }
+
+    /// Returns the number of generations [step_back] can currently go back.
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Pattern, Rule, Universe};
+
+/// Side length of a chunk, in cells.
+const CHUNK_SIZE: i32 = 64;
+
+/// Smallest and largest chunk coordinates whose cells all have i32 coordinates.
+const MIN_CHUNK: i32 = i32::MIN / CHUNK_SIZE;
+const MAX_CHUNK: i32 = i32::MAX / CHUNK_SIZE;
+
+/// Row-major CHUNK_SIZE x CHUNK_SIZE cells.
+type Chunk = Vec<Cell>;
+
+/// The smallest rectangle containing all live cells of an [InfiniteUniverse].
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// An unbounded plane of cells. Cells are stored in square chunks, keyed by
+/// their signed chunk coordinates, which are created as patterns grow into
+/// them and dropped once they're empty. Cells beyond the range of i32
+/// coordinates are always dead.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default)]
+pub struct InfiniteUniverse {
+    chunks: HashMap<(i32, i32), Chunk>,
+    rule: Rule,
+}
+
+#[wasm_bindgen]
+impl InfiniteUniverse {
+    /// Creates an empty plane following Conway's rules.
+    pub fn new() -> InfiniteUniverse {
+        InfiniteUniverse::default()
+    }
+
+    /// Apply the current rule once to all cells in this.
+    pub fn tick(&mut self) {
+        // Only chunks with live cells and their neighbors can have live cells
+        // in the next generation.
+        let candidates: HashSet<(i32, i32)> = self.chunks.keys()
+            .flat_map(|&(x, y)| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy))))
+            .filter(|(x, y)| (MIN_CHUNK..=MAX_CHUNK).contains(x) && (MIN_CHUNK..=MAX_CHUNK).contains(y))
+            .collect();
+
+        self.chunks = candidates.into_iter()
+            .map(|coords| (coords, self.next_chunk(coords)))
+            .filter(|(_, chunk)| chunk.contains(&Cell::Alive))
+            .collect();
+    }
+
+    /// Sets the rule applied by [tick]. Rules where empty space comes alive
+    /// (B0) are rejected, because they would fill the infinite plane, as are
+    /// Generations and Larger than Life rules.
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), String> {
+        let rule = Rule::parse(rulestring)?;
+
+        if rule.has_b0() {
+            return Err(format!("Infinite universes don't support B0 rules, such as {}", rule));
+        }
+
+        if let Some(family) = rule.family() {
+            return Err(format!("Infinite universes don't support {} rules, such as {}", family, rule));
+        }
+
+        self.rule = rule;
+        Ok(())
+    }
+
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    pub fn get_cell(&self, x: i32, y: i32) -> Cell {
+        let (chunk, idx) = Self::locate(x, y);
+
+        match self.chunks.get(&chunk) {
+            Some(chunk) => chunk[idx],
+            None => Cell::Dead,
+        }
+    }
+
+    pub fn set_cell(&mut self, x: i32, y: i32, cell: Cell) {
+        let (coords, idx) = Self::locate(x, y);
+
+        if cell == Cell::Dead && !self.chunks.contains_key(&coords) {
+            return;
+        }
+
+        let chunk = self.chunks.entry(coords).or_insert_with(|| vec![Cell::Dead; (CHUNK_SIZE * CHUNK_SIZE) as usize]);
+        chunk[idx] = cell;
+
+        if cell == Cell::Dead && !chunk.contains(&Cell::Alive) {
+            self.chunks.remove(&coords);
+        }
+    }
+
+    pub fn toggle_cell(&mut self, x: i32, y: i32) {
+        let toggled = match self.get_cell(x, y) {
+            Cell::Alive => Cell::Dead,
+            Cell::Dead => Cell::Alive,
+        };
+
+        self.set_cell(x, y, toggled);
+    }
+
+    /// Sets all cells to Cell::Dead
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+
+    pub fn population(&self) -> u64 {
+        self.chunks.values()
+            .map(|chunk| chunk.iter().filter(|&&cell| cell == Cell::Alive).count() as u64)
+            .sum()
+    }
+
+    /// Returns the number of chunks currently allocated.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns the smallest rectangle containing all live cells, or None if
+    /// there are none.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let mut bounds: Option<(i32, i32, i32, i32)> = None;
+
+        for (x, y) in self.live_cells() {
+            bounds = Some(match bounds {
+                Some((left, top, right, bottom)) => (left.min(x), top.min(y), right.max(x), bottom.max(y)),
+                None => (x, y, x, y),
+            });
+        }
+
+        bounds.map(|(left, top, right, bottom)| BoundingBox {
+            left,
+            top,
+            width: (i64::from(right) - i64::from(left) + 1) as u32,
+            height: (i64::from(bottom) - i64::from(top) + 1) as u32,
+        })
+    }
+
+    /// Loads the RLE-encoded pattern [rle] with its top-left corner at ([x], [y]),
+    /// overwriting all cells in the rectangle it covers. If [rle] specifies a
+    /// rule, this switches to that rule.
+    pub fn load_rle(&mut self, rle: &str, x: i32, y: i32) -> Result<(), String> {
+        let pattern = Pattern::from_rle(rle)?;
+
+        if let Some(rule) = pattern.rule {
+            self.set_rule(&rule.to_string())?;
+        }
+
+        for py in 0..pattern.height() {
+            for px in 0..pattern.width() {
+                let offset = |position: i32, delta: u32| {
+                    i32::try_from(i64::from(position) + i64::from(delta)).map_err(|_| "Pattern extends beyond the plane".to_string())
+                };
+
+                self.set_cell(offset(x, px)?, offset(y, py)?, pattern.get_cell_at(px, py));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the cells within [bounding_box] as RLE, including the current rule.
+    pub fn to_rle(&self) -> String {
+        let mut pattern = Pattern::new(0, 0);
+        pattern.rule = Some(self.rule);
+
+        if let Some(bounds) = self.bounding_box() {
+            pattern.resize(bounds.width, bounds.height);
+
+            for (x, y) in self.live_cells() {
+                let x = i64::from(x) - i64::from(bounds.left);
+                let y = i64::from(y) - i64::from(bounds.top);
+
+                pattern.set_cell_at(x as u32, y as u32, Cell::Alive);
+            }
+        }
+
+        pattern.to_rle()
+    }
+
+    /// Clears [universe] and copies the cells of this into it, such that
+    /// ([left], [top]) lands on [universe]'s top-left cell.
+    pub fn project_into(&self, universe: &mut Universe, left: i32, top: i32) {
+        universe.reset_cells();
+
+        for (x, y) in self.live_cells() {
+            let x = i64::from(x) - i64::from(left);
+            let y = i64::from(y) - i64::from(top);
+
+            if (0..i64::from(universe.width())).contains(&x) && (0..i64::from(universe.height())).contains(&y) {
+                universe.write_cell(x as u32, y as u32, Cell::Alive);
+            }
+        }
+    }
+}
+
+// Private impl
+impl InfiniteUniverse {
+    /// Returns the coordinates of the chunk containing ([x], [y]) and the
+    /// index of the cell within it.
+    fn locate(x: i32, y: i32) -> ((i32, i32), usize) {
+        let chunk = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+        let idx = y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE);
+
+        (chunk, idx as usize)
+    }
+
+    /// Returns the coordinates of all live cells.
+    fn live_cells(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.chunks.iter().flat_map(|(&(chunk_x, chunk_y), chunk)| {
+            chunk.iter().enumerate()
+                .filter(|(_, &cell)| cell == Cell::Alive)
+                .filter_map(move |(idx, _)| {
+                    let (idx, size) = (idx as i64, i64::from(CHUNK_SIZE));
+                    let x = i64::from(chunk_x) * size + idx % size;
+                    let y = i64::from(chunk_y) * size + idx / size;
+
+                    Some((i32::try_from(x).ok()?, i32::try_from(y).ok()?))
+                })
+        })
+    }
+
+    /// Computes the next generation of the chunk at [coords].
+    fn next_chunk(&self, (chunk_x, chunk_y): (i32, i32)) -> Chunk {
+        let mut neighborhood = [[None; 3]; 3];
+        for (dy, row) in neighborhood.iter_mut().enumerate() {
+            for (dx, chunk) in row.iter_mut().enumerate() {
+                *chunk = self.chunks.get(&(chunk_x + dx as i32 - 1, chunk_y + dy as i32 - 1));
+            }
+        }
+
+        // Takes coordinates relative to the top-left of the chunk, which may
+        // be up to one cell outside of it.
+        let alive = |x: i32, y: i32| -> bool {
+            let row = &neighborhood[(y.div_euclid(CHUNK_SIZE) + 1) as usize];
+            let idx = y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE);
+
+            match row[(x.div_euclid(CHUNK_SIZE) + 1) as usize] {
+                Some(chunk) => chunk[idx as usize] == Cell::Alive,
+                None => false,
+            }
+        };
+
+        let mut next = vec![Cell::Dead; (CHUNK_SIZE * CHUNK_SIZE) as usize];
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let mut neighborhood = 0;
+
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        if alive(x + dx, y + dy) {
+                            neighborhood |= 1 << (3 * (dy + 1) + (dx + 1));
+                        }
+                    }
+                }
+
+                next[(y * CHUNK_SIZE + x) as usize] = self.rule.next_state(neighborhood);
+            }
+        }
+
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BoundingBox, InfiniteUniverse};
+    use crate::testing::live_cells;
+    use crate::{Cell, Topology, Universe};
+
+    const BLINKER: &str = "x = 3, y = 1\n3o!";
+
+    #[test]
+    fn matches_plane_universe() {
+        // The soup straddles chunks on both sides of the origin, and is far
+        // enough from the edges of the universe not to reach them.
+        let (left, top) = (-100, -70);
+        let mut universe = Universe::empty(160, 160);
+        universe.set_topology(Topology::Plane);
+        universe.set_rule("B36/S23").unwrap();
+        universe.randomize_region(60, 60, 40, 40, 0.4, 3).unwrap();
+
+        let mut infinite = InfiniteUniverse::new();
+        infinite.set_rule("B36/S23").unwrap();
+        for (x, y) in live_cells(&universe) {
+            infinite.set_cell(x as i32 + left, y as i32 + top, Cell::Alive);
+        }
+
+        let mut projected = Universe::empty(160, 160);
+
+        for generation in 0..30 {
+            universe.tick();
+            infinite.tick();
+
+            infinite.project_into(&mut projected, left, top);
+            assert_eq!(live_cells(&projected), live_cells(&universe), "generation {}", generation);
+            assert_eq!(infinite.population(), u64::from(universe.population()));
+        }
+    }
+
+    #[test]
+    fn bounds_live_cells() {
+        let mut infinite = InfiniteUniverse::new();
+        assert_eq!(infinite.bounding_box(), None);
+
+        infinite.load_rle("x = 3, y = 3\nbo$2bo$3o!", -65, 62).unwrap();
+        assert_eq!(infinite.bounding_box(), Some(BoundingBox { left: -65, top: 62, width: 3, height: 3 }));
+
+        for _ in 0..4 {
+            infinite.tick();
+        }
+        assert_eq!(infinite.bounding_box(), Some(BoundingBox { left: -64, top: 63, width: 3, height: 3 }));
+        assert_eq!(infinite.to_rle(), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+
+        infinite.clear();
+        assert_eq!(infinite.bounding_box(), None);
+        assert_eq!(infinite.chunk_count(), 0);
+    }
+
+    #[test]
+    fn ticks_at_coordinate_edges() {
+        for &(x, y) in &[(i32::MAX - 2, i32::MAX - 1), (i32::MIN, i32::MIN + 1), (i32::MAX - 2, i32::MIN + 1)] {
+            let mut infinite = InfiniteUniverse::new();
+            infinite.load_rle(BLINKER, x, y).unwrap();
+
+            infinite.tick();
+            assert_eq!(infinite.bounding_box(), Some(BoundingBox { left: x + 1, top: y - 1, width: 1, height: 3 }));
+
+            infinite.tick();
+            assert_eq!(infinite.bounding_box(), Some(BoundingBox { left: x, top: y, width: 3, height: 1 }));
+        }
+    }
+
+    #[test]
+    fn cells_beyond_coordinate_edges_are_dead() {
+        // A vertical blinker against the right edge can't grow past it.
+        let mut infinite = InfiniteUniverse::new();
+        for y in 0..3 {
+            infinite.set_cell(i32::MAX, y, Cell::Alive);
+        }
+
+        infinite.tick();
+        assert_eq!(infinite.population(), 2);
+        assert_eq!(infinite.bounding_box(), Some(BoundingBox { left: i32::MAX - 1, top: 1, width: 2, height: 1 }));
+        assert_eq!(infinite.to_rle(), "x = 2, y = 1, rule = B3/S23\n2o!\n");
+
+        infinite.tick();
+        assert_eq!(infinite.population(), 0);
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+use wasm_bindgen::prelude::*;
+
+use crate::Universe;
+
+/// Default for the memory used by undoable transactions, in bytes.
+pub const DEFAULT_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// A change to the state of a single cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CellEdit {
+    pub x: u32,
+    pub y: u32,
+    pub before: u8,
+    pub after: u8,
+}
+
+/// Edits that are undone and redone together.
+type Transaction = Vec<CellEdit>;
+
+/// Records edits to a universe's cells as transactions that can be undone
+/// and redone. Transactions nest: edits are grouped until the outermost
+/// transaction ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Journal {
+    undo_stack: VecDeque<Transaction>,
+    redo_stack: Vec<Transaction>,
+
+    /// Edits of the open transaction.
+    open: Transaction,
+    depth: u32,
+
+    /// Whether the open transaction outgrew [memory_limit] and was dropped,
+    /// so that its remaining edits aren't recorded either.
+    overflowed: bool,
+
+    /// Memory used by [undo_stack] and [redo_stack], in bytes.
+    memory_used: usize,
+    memory_limit: usize,
+}
+
+impl Journal {
+    pub fn new(memory_limit: usize) -> Journal {
+        Journal {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+
+            open: Vec::new(),
+            depth: 0,
+            overflowed: false,
+
+            memory_used: 0,
+            memory_limit,
+        }
+    }
+
+    pub fn begin(&mut self) {
+        self.depth += 1;
+    }
+
+    /// Ends the innermost open transaction, committing the outermost one.
+    pub fn end(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+
+        if self.depth == 0 {
+            self.overflowed = false;
+        }
+
+        if self.depth == 0 && !self.open.is_empty() {
+            let transaction = std::mem::take(&mut self.open);
+
+            self.memory_used += Self::size_of(&transaction);
+            self.undo_stack.push_back(transaction);
+
+            let redo_stack = std::mem::take(&mut self.redo_stack);
+            self.memory_used -= redo_stack.iter().map(Self::size_of).sum::<usize>();
+
+            self.enforce_memory_limit();
+        }
+    }
+
+    /// Ends all open transactions.
+    pub fn end_all(&mut self) {
+        self.depth = 1;
+        self.end();
+    }
+
+    /// Adds [edit] to the open transaction. Edits made while no transaction is
+    /// open aren't recorded.
+    ///
+    /// A transaction larger than [memory_limit] can't be undone, so it's
+    /// dropped as soon as it outgrows the limit. Earlier transactions are
+    /// forgotten too, as undoing them would skip over its edits.
+    pub fn record(&mut self, edit: CellEdit) {
+        if self.depth == 0 || self.overflowed {
+            return;
+        }
+
+        self.open.push(edit);
+
+        if Self::size_of(&self.open) > self.memory_limit {
+            self.clear();
+            self.overflowed = true;
+        }
+    }
+
+    /// Forgets all transactions, including the edits of the open one. Called
+    /// whenever cells change other than by an edit, since undoing an edit
+    /// made before then would restore states that are out of date.
+    pub fn clear(&mut self) {
+        self.open = Vec::new();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.memory_used = 0;
+    }
+
+    /// Removes the most recent transaction, returning its edits in the order
+    /// they should be reverted.
+    pub fn undo(&mut self) -> Option<Vec<CellEdit>> {
+        self.end_all();
+
+        let transaction = self.undo_stack.pop_back()?;
+        let edits = transaction.iter().rev().copied().collect();
+        self.redo_stack.push(transaction);
+
+        Some(edits)
+    }
+
+    /// Removes the most recently undone transaction, returning its edits in
+    /// the order they should be reapplied.
+    pub fn redo(&mut self) -> Option<Vec<CellEdit>> {
+        self.end_all();
+
+        let transaction = self.redo_stack.pop()?;
+        let edits = transaction.clone();
+        self.undo_stack.push_back(transaction);
+
+        Some(edits)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() || !self.open.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    pub fn set_memory_limit(&mut self, memory_limit: usize) {
+        self.memory_limit = memory_limit;
+        self.enforce_memory_limit();
+    }
+
+    /// Forgets the oldest transactions until no more than [memory_limit] bytes are used.
+    fn enforce_memory_limit(&mut self) {
+        while self.memory_used > self.memory_limit {
+            let oldest = match self.undo_stack.pop_front() {
+                Some(transaction) => transaction,
+                None => self.redo_stack.remove(0),
+            };
+
+            self.memory_used -= Self::size_of(&oldest);
+        }
+    }
+
+    fn size_of(transaction: &Transaction) -> usize {
+        transaction.len() * size_of::<CellEdit>()
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Starts grouping edits (e.g. those of one pointer stroke) into a single
+    /// transaction for [undo] and [redo]. Transactions nest; the group is
+    /// committed by the outermost [end_transaction].
+    pub fn begin_transaction(&mut self) {
+        self.journal.begin();
+    }
+
+    pub fn end_transaction(&mut self) {
+        self.journal.end();
+    }
+
+    /// Reverts the most recent transaction. Returns false if there's nothing to
+    /// undo, as after [tick], [resize_to] or [step_back], which forget all
+    /// transactions.
+    pub fn undo(&mut self) -> bool {
+        match self.journal.undo() {
+            Some(edits) => {
+                for edit in edits {
+                    self.apply_edit(edit.x, edit.y, edit.before);
+                }
+
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Reapplies the most recently undone transaction. Returns false if there's
+    /// nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.journal.redo() {
+            Some(edits) => {
+                for edit in edits {
+                    self.apply_edit(edit.x, edit.y, edit.after);
+                }
+
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.journal.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.journal.can_redo()
+    }
+
+    /// Sets the maximum memory, in bytes, used to remember transactions.
+    /// The oldest transactions are forgotten first.
+    pub fn set_undo_memory_limit(&mut self, bytes: usize) {
+        self.journal.set_memory_limit(bytes);
+    }
+
+    pub fn undo_memory_limit(&self) -> usize {
+        self.journal.memory_limit()
+    }
+}
+
+// Private impl
+impl Universe {
+    /// Sets the cell at ([x], [y]) as part of an undo or redo, ignoring cells
+    /// that are no longer in bounds after a resize.
+    fn apply_edit(&mut self, x: u32, y: u32, state: u8) {
+        if x < self.width && y < self.height {
+            self.write_state(x, y, state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+
+    use super::CellEdit;
+    use crate::{Cell, HashLife, InfiniteUniverse, Pattern, Topology, Universe};
+
+    const GLIDER: &str = "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!";
+
+    #[test]
+    fn undoes_and_redoes_transactions() {
+        let mut universe = Universe::empty(8, 8);
+
+        universe.begin_transaction();
+        universe.set_cell_at(1, 1, Cell::Alive);
+        universe.set_cell_at(2, 1, Cell::Alive);
+        universe.end_transaction();
+        universe.load_rle(GLIDER, 4, 4).unwrap();
+
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 2);
+        assert!(universe.undo());
+        assert_eq!(universe.population(), 0);
+        assert!(!universe.can_undo());
+
+        assert!(universe.redo());
+        assert_eq!(universe.population(), 2);
+        assert!(universe.can_redo());
+    }
+
+    #[test]
+    fn loaded_universes_cannot_undo() {
+        let pattern = Pattern::from_rle(GLIDER).unwrap();
+        let universes = [
+            Universe::from_pattern(&pattern),
+            Universe::from_rle(GLIDER).unwrap(),
+            Universe::from_plaintext(&pattern.to_plaintext()).unwrap(),
+            Universe::from_life106(&pattern.to_life106()).unwrap(),
+            Universe::from_apgcode("xq4_153").unwrap(),
+            Universe::from_url_code(&pattern.to_url_code()).unwrap(),
+            Universe::from_pattern_string(GLIDER).unwrap(),
+        ];
+
+        for mut universe in universes {
+            assert_eq!(universe.population(), 5);
+            assert!(!universe.can_undo());
+            assert!(!universe.undo());
+            assert_eq!(universe.population(), 5);
+        }
+    }
+
+    #[test]
+    fn forgets_transactions_when_cells_change_otherwise() {
+        let blinker = "x = 3, y = 1\n3o!";
+        let changes: [fn(&mut Universe); 5] = [
+            |universe| universe.tick(),
+            |universe| universe.resize_to(12, 10),
+            |universe| universe.reset_cells(),
+            |universe| HashLife::from_universe(universe).unwrap().project_into(universe, 0, 0),
+            |universe| InfiniteUniverse::new().project_into(universe, 0, 0),
+        ];
+
+        for change in &changes {
+            let mut universe = Universe::empty(10, 10);
+            universe.set_topology(Topology::Plane);
+            universe.load_rle(blinker, 3, 4).unwrap();
+            assert!(universe.can_undo());
+
+            change(&mut universe);
+            let cells = universe.cells.to_vec();
+
+            assert!(!universe.can_undo());
+            assert!(!universe.undo());
+            assert_eq!(universe.cells.to_vec(), cells);
+        }
+    }
+
+    #[test]
+    fn step_back_forgets_transactions() {
+        let mut universe = Universe::empty(10, 10);
+        universe.set_history_depth(4);
+        universe.tick();
+
+        universe.set_cell_at(1, 1, Cell::Alive);
+        universe.tick();
+        assert_eq!(universe.step_back(1), 1);
+
+        assert!(!universe.undo());
+        assert_eq!(universe.get_cell_at(1, 1), Cell::Alive);
+    }
+
+    #[test]
+    fn forgets_open_transaction_on_tick() {
+        let mut universe = Universe::empty(10, 10);
+
+        universe.begin_transaction();
+        universe.set_cell_at(1, 1, Cell::Alive);
+        universe.tick();
+        universe.set_cell_at(5, 5, Cell::Alive);
+        universe.end_transaction();
+
+        assert!(universe.undo());
+        assert_eq!(universe.get_cell_at(5, 5), Cell::Dead);
+        assert!(!universe.undo());
+    }
+
+    #[test]
+    fn drops_transactions_over_memory_limit() {
+        let mut universe = Universe::empty(64, 64);
+        universe.set_undo_memory_limit(100 * size_of::<CellEdit>());
+
+        universe.set_cell_at(0, 0, Cell::Alive);
+        assert!(universe.can_undo());
+
+        // Far more edits than fit within the limit.
+        universe.randomize(0.5, 1).unwrap();
+        assert!(universe.journal.open.is_empty());
+        assert!(!universe.can_undo());
+        assert!(!universe.undo());
+
+        // Later transactions are recorded as usual.
+        universe.set_cell_at(0, 0, Cell::Dead);
+        universe.set_cell_at(0, 0, Cell::Alive);
+        assert!(universe.undo());
+        assert_eq!(universe.get_cell_at(0, 0), Cell::Dead);
+    }
+}
@@ -3,6 +3,52 @@ use wasm_bindgen::prelude::*;
 use web_sys::ImageData;
 use wasm_bindgen::Clamped;
 
+use backend::Cells;
+use buffers::ChangedCells;
+use cycles::Cycles;
+use history::History;
+use journal::{CellEdit, Journal};
+use stats::{Stats, TickCounts};
+use tiles::{Tiles, TILE_SIZE};
+
+mod apgcode;
+mod backend;
+mod bitgrid;
+mod buffers;
+mod census;
+mod cycles;
+mod hashlife;
+mod hensel;
+mod history;
+mod infinite;
+mod journal;
+mod life106;
+mod ltl;
+mod map;
+mod neighborhood;
+mod pattern;
+mod plaintext;
+mod rle;
+mod rule;
+mod share;
+mod snapshot;
+mod soup;
+mod stats;
+#[cfg(test)]
+mod testing;
+mod tiles;
+mod topology;
+
+pub use backend::Backend;
+pub use census::{Census, Connectivity};
+pub use cycles::CycleStatus;
+pub use hashlife::HashLife;
+pub use infinite::{BoundingBox, InfiniteUniverse};
+pub use neighborhood::Neighborhood;
+pub use pattern::Pattern;
+pub use rule::Rule;
+pub use topology::Topology;
+
 const DEFAULT_SQUARE_SIZE: u32 = 8;
 const DEFAULT_SPACING: u32 = 1;
 
@@ -13,6 +59,7 @@ pub fn main() {
 }
 
 #[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Color4 {
     red: u8,
     green: u8,
@@ -40,58 +87,215 @@ pub enum Cell {
     Alive = 1,
 }
 
+impl Cell {
+    /// Returns the cell in [state] (see [Rule]), where dying cells are dead.
+    pub(crate) fn from_state(state: u8) -> Cell {
+        if state == 1 {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub struct Universe {
-    buffered_cells_: Vec<Cell>,
+    buffered_cells_: Cells,
 
-    cells: Vec<Cell>,
+    cells: Cells,
     width: u32,
     height: u32,
 
     square_size_px: u32,
     square_spacing_px: u32,
+
+    /// Colors of dying states, by state, for [render_cells].
+    state_colors: Vec<Option<Color4>>,
+
+    rule: Rule,
+    topology: Topology,
+
+    /// Tiles changed by the last tick or edited since.
+    tiles: Tiles,
+
+    journal: Journal,
+
+    generation: u64,
+    history: History,
+
+    stats: Stats,
+    cycles: Cycles,
+
+    /// Row-major states of bit-packed cells, for [cells_ptr].
+    unpacked_cells: Vec<u8>,
+    changed_cells: ChangedCells,
 }
 
 #[wasm_bindgen]
 impl Universe {
-    /// Apply the rules of the game of life once to all cells in this.
+    /// Apply the current rule once to all cells in this.
+    ///
+    /// Only tiles that changed in the last generation, and their neighbors,
+    /// are recomputed. Elsewhere the buffered cells (from the last generation)
+    /// already match the current ones, and so are also the next generation.
     pub fn tick(&mut self) {
-        for x in 0..self.width {
-            for y in 0..self.height {
-                let idx = self.get_cell_idx(x, y);
+        let recompute = self.tiles.to_recompute(self.topology != Topology::Plane);
+        let mut changed = vec![false; recompute.len()];
+        let mut counts = TickCounts::default();
+
+        self.cycles.observe(self.generation, self.topology);
+
+        if let Some(ltl) = self.rule.larger_than_life().copied() {
+            // Larger ranges reach beyond neighboring tiles, so all cells are
+            // recomputed.
+            self.tick_larger_than_life(&ltl, &mut changed, &mut counts);
+        } else if let (Cells::BitPacked(cells), Cells::BitPacked(buffered), Some(masks)) = (&self.cells, &mut self.buffered_cells_, self.rule.totalistic_masks(Neighborhood::Moore)) {
+            // step_into only counts neighbors, so rules that depend on their
+            // arrangement take the cell-by-cell path below.
+            cells.step_into(masks, &recompute, &mut changed, &mut counts, buffered);
+
+            // step_into treats everything beyond the edges as dead, so edge cells
+            // need to be recomputed for other topologies.
+            if self.topology != Topology::Plane {
+                self.tick_edges(&mut changed, &mut counts);
+            }
+        } else {
+            for tile_y in (0..self.height).step_by(TILE_SIZE as usize) {
+                for tile_x in (0..self.width).step_by(TILE_SIZE as usize) {
+                    let tile = self.tiles.tile_of(tile_x, tile_y);
+                    if !recompute[tile] {
+                        continue;
+                    }
 
-                self.buffered_cells_[idx] = match (self.cells[idx], self.get_live_neighbor_count(x, y)) {
-                    (_, x) if x < 2 => Cell::Dead,
-                    (Cell::Alive, 2) | (_, 3) => Cell::Alive,
-                    (_, x) if x > 3 => Cell::Dead,
-                    (otherwise, _) => otherwise,
+                    for y in tile_y..(tile_y + TILE_SIZE).min(self.height) {
+                        for x in tile_x..(tile_x + TILE_SIZE).min(self.width) {
+                            changed[tile] |= self.tick_cell(x, y, &mut counts);
+                        }
+                    }
                 }
             }
         }
 
+        self.record_tick_changes(&changed);
+        self.tiles.set_changed(changed);
+        self.journal.clear();
         std::mem::swap(&mut self.buffered_cells_, &mut self.cells);
+        self.generation += 1;
+        self.stats.record_tick(counts);
+        self.cycles.observe(self.generation, self.topology);
+    }
+
+    /// Returns the number of times this has been ticked, less the number of
+    /// generations stepped back.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the side length of the square tiles reported by [active_tiles].
+    pub fn tile_size(&self) -> u32 {
+        TILE_SIZE
+    }
+
+    /// Returns the indices of the tiles that changed in the last generation or
+    /// were edited since. Tiles are numbered in row-major order, with
+    /// ceil(width / tile_size) tiles per row.
+    pub fn active_tiles(&self) -> Vec<u32> {
+        self.tiles.changed_indices()
+    }
+
+    /// Sets the rule applied by [tick] from a rulestring such as "B3/S23",
+    /// "B36/S23" or the legacy "23/3", an isotropic non-totalistic rulestring
+    /// such as "B2-a/S12", a MAP rulestring, a Generations rulestring such as
+    /// "B2/S/C3", or a Larger than Life rulestring such as
+    /// "R5,C0,M1,S34..58,B34..45,NM". Rulestrings like "B2/S34H" and "B1/S1V"
+    /// count the hexagonal or von Neumann neighborhood.
+    pub fn set_rule(&mut self, rulestring: &str) -> Result<(), String> {
+        self.use_rule(Rule::parse(rulestring)?);
+        Ok(())
+    }
+
+    /// Returns the current rule as a canonical rulestring, in one of the forms
+    /// accepted by [set_rule].
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Sets how the edges of this are joined. Affects [tick], [get_cell_at]
+    /// and [toggle_cell_at].
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.tiles.mark_all();
+        self.cycles.reset();
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
     }
 
+    /// Switches how this stores its cells, keeping their states. Generations
+    /// rules need Backend::Bytes, so other backends are ignored while one is
+    /// in use.
+    pub fn set_backend(&mut self, backend: Backend) {
+        if backend != self.backend() && (backend == Backend::Bytes || self.rule.states() == 2) {
+            self.cells = Cells::new(backend, self.cells.to_vec(), self.width, self.height);
+            self.buffered_cells_ = self.cells.clone();
+            self.tiles.mark_all();
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.cells.backend()
+    }
+
+    /// Returns the cell at ([x], [y]). Points beyond the edges are mapped
+    /// onto this' surface according to its topology, or are dead if they
+    /// fall off of it.
     pub fn get_cell_at(&self, x: u32, y: u32) -> Cell {
-        self.cells[self.get_cell_idx(x, y)]
+        match self.resolve(x.into(), y.into()) {
+            Some((x, y)) => self.cells.get(x, y, self.width),
+            None => Cell::Dead,
+        }
+    }
+
+    /// Returns the state of the cell at ([x], [y]): 0 if dead, 1 if alive, or
+    /// one of the dying states of a Generations rule (see [Rule]). Points are
+    /// mapped as in [get_cell_at].
+    pub fn get_state_at(&self, x: u32, y: u32) -> u8 {
+        match self.resolve(x.into(), y.into()) {
+            Some((x, y)) => self.cells.get_state(x, y, self.width),
+            None => 0,
+        }
+    }
+
+    /// Returns the number of states of the current rule: 2, or more for
+    /// Generations rules.
+    pub fn states(&self) -> u8 {
+        self.rule.states()
     }
 
     /// Sets the cell at ([x], [y]) to [cell_type], where
     /// x ∈ [0, self.width) and y ∈ [0, self.height).
     pub fn set_cell_at(&mut self, x: u32, y: u32, cell_type: Cell) {
         if x < self.width && y < self.height {
-            let idx = self.get_cell_idx(x, y);
-            self.cells[idx] = cell_type;
+            self.journal.begin();
+            self.edit_cell(x, y, cell_type);
+            self.journal.end();
         }
     }
 
+    /// Toggles the cell at ([x], [y]), mapping points beyond the edges of this
+    /// as in [get_cell_at].
     pub fn toggle_cell_at(&mut self, x: u32, y: u32) {
-        let new_value = match self.get_cell_at(x, y) {
-            Cell::Alive => Cell::Dead,
-            Cell::Dead => Cell::Alive,
-        };
-
-        self.set_cell_at(x, y, new_value);
+        if let Some((x, y)) = self.resolve(x.into(), y.into()) {
+            let toggled = match self.cells.get(x, y, self.width) {
+                Cell::Alive => Cell::Dead,
+                Cell::Dead => Cell::Alive,
+            };
+
+            self.journal.begin();
+            self.edit_cell(x, y, toggled);
+            self.journal.end();
+        }
     }
 
     /// Toggles all cells along the line between (x1, y1) and (x2, y2), but cells
@@ -101,6 +305,8 @@ impl Universe {
             return;
         }
 
+        self.journal.begin();
+
         let mut delta_y = (y2 as f64) - (y1 as f64);
         let mut delta_x = (x2 as f64) - (x1 as f64);
 
@@ -131,31 +337,52 @@ impl Universe {
                 self.toggle_cell_at(x.round() as u32, y);
             }
         }
+
+        self.journal.end();
     }
 
     /// Sets all cells to Cell::Dead
     pub fn clear(&mut self) {
+        self.journal.begin();
+
         for x in 0..self.width {
             for y in 0..self.height {
-                self.set_cell_at(x, y, Cell::Dead);
+                self.edit_cell(x, y, Cell::Dead);
             }
         }
+
+        self.journal.end();
     }
 
     /// Render cells, pixel-by-pixel
+    ///
+    /// Under Generations rules, rendering Cell::Alive also renders dying cells,
+    /// in the colors given to [set_state_color], or else in [color], fading
+    /// out as the cells die.
     pub fn render_cells(&self, cell_type: Cell, color: &Color4, ctx: &web_sys::CanvasRenderingContext2d) {
         let square_size = self.square_size_px + self.square_spacing_px;
-        let mut data: Vec<u8> = (0..(self.width * 4 * self.height * square_size * square_size)).map(|_| { 0 as u8 }).collect();
+        let mut data: Vec<u8> = (0..(self.width * 4 * self.height * square_size * square_size)).map(|_| { 0_u8 }).collect();
+
+        let mut colors = vec![None; self.rule.states().into()];
+        colors[cell_type as usize] = Some(*color);
+
+        if cell_type == Cell::Alive {
+            let states = u32::from(self.rule.states());
+
+            for state in 2..states {
+                let faded = Color4 { alpha: (u32::from(color.alpha) * (states - state) / (states - 1)) as u8, ..*color };
+                colors[state as usize] = Some(self.state_colors.get(state as usize).copied().flatten().unwrap_or(faded));
+            }
+        }
 
         for x in 0..self.width {
             let square_x = x * square_size + self.square_spacing_px;
 
             for y in 0..self.height {
-                let cell = self.get_cell_at(x, y);
-
-                if cell != cell_type {
-                    continue;
-                }
+                let color = match colors.get(usize::from(self.get_state_at(x, y))) {
+                    Some(Some(color)) => color,
+                    _ => continue,
+                };
 
                 let square_y = y * square_size + self.square_spacing_px;
 
@@ -178,15 +405,20 @@ impl Universe {
 
     /// Render cells using fill_rect
     pub fn fill_cells(&self, cell_type: Cell, ctx: &web_sys::CanvasRenderingContext2d) {
+        self.fill_state(cell_type as u8, ctx);
+    }
+
+    /// Renders the cells in [state] (see [get_state_at]) using fill_rect, so
+    /// that each of the dying states of a Generations rule can be filled
+    /// differently.
+    pub fn fill_state(&self, state: u8, ctx: &web_sys::CanvasRenderingContext2d) {
         let square_size = self.square_size_px + self.square_spacing_px;
 
         for x in 0..self.width {
             let square_x = x * square_size + self.square_spacing_px;
 
             for y in 0..self.height {
-                let cell = self.get_cell_at(x, y);
-
-                if cell != cell_type {
+                if self.get_state_at(x, y) != state {
                     continue;
                 }
 
@@ -197,6 +429,22 @@ impl Universe {
         }
     }
 
+    /// Sets the color [render_cells] uses for dying cells in [state].
+    pub fn set_state_color(&mut self, state: u8, color: &Color4) {
+        let idx = usize::from(state);
+
+        if self.state_colors.len() <= idx {
+            self.state_colors.resize(idx + 1, None);
+        }
+
+        self.state_colors[idx] = Some(*color);
+    }
+
+    /// Forgets the colors given to [set_state_color].
+    pub fn clear_state_colors(&mut self) {
+        self.state_colors.clear();
+    }
+
     pub fn set_square_size(&mut self, size: u32) {
         self.square_size_px = size;
     }
@@ -213,30 +461,69 @@ impl Universe {
 
     /// Create a new universe with initial data based on that in [template].
     pub fn resize_to(&mut self, width: u32, height: u32) {
-        let mut cells: Vec<Cell> = (0..width*height)
+        let cells: Vec<u8> = (0..width*height)
                 .map(|i: u32| { ( i % width, i / width ) })
                 .map(|(x, y)| {
-                    self.get_cell_at(x, y)
+                    self.get_state_at(x, y)
                 })
                 .collect();
+        let mut cells = Cells::new(self.backend(), cells, width, height);
         let mut background_cells = cells.clone();
 
         self.width = width;
         self.height = height;
+        self.tiles = Tiles::new(width, height);
+        self.history.clear();
+        self.journal.clear();
         std::mem::swap(&mut self.cells, &mut cells);
         std::mem::swap(&mut self.buffered_cells_, &mut background_cells);
+        self.stats.reset_population(self.count_population());
+        self.changed_cells.record_all((width * height) as usize);
+
+        self.rebuild_cycles();
     }
 
     pub fn new(width: u32, height: u32) -> Universe {
-        let cells: Vec<Cell> = (0..width * height)
+        let cells: Vec<u8> = (0..width * height)
                 .map(|i| {
                     if i % 2 == 0 || i % 7 == 0 {
-                        Cell::Alive
+                        Cell::Alive as u8
                     } else {
-                        Cell::Dead
+                        Cell::Dead as u8
                     }
                 })
                 .collect();
+
+        Universe::with_states(width, height, cells)
+    }
+
+    /// Creates a [width] x [height] universe of dead cells.
+    pub fn empty(width: u32, height: u32) -> Universe {
+        Universe::with_states(width, height, vec![Cell::Dead as u8; (width * height) as usize])
+    }
+
+    /// Creates a [width] x [height] universe from row-major [cells], where
+    /// 0 is Cell::Dead and 1 is Cell::Alive.
+    pub fn from_cells(width: u32, height: u32, cells: &[u8]) -> Result<Universe, String> {
+        let expected = width as usize * height as usize;
+        if cells.len() != expected {
+            return Err(format!("Expected {} cells for a {} x {} universe, got {}", expected, width, height, cells.len()));
+        }
+
+        if let Some(idx) = cells.iter().position(|&state| state > Cell::Alive as u8) {
+            return Err(format!("Invalid state {} for the cell at ({}, {})", cells[idx], idx % width as usize, idx / width as usize));
+        }
+
+        Ok(Universe::with_states(width, height, cells.to_vec()))
+    }
+}
+
+// Private impl
+impl Universe {
+    /// Creates a [width] x [height] universe from row-major [states].
+    pub(crate) fn with_states(width: u32, height: u32, states: Vec<u8>) -> Universe {
+        let population = states.iter().filter(|&&state| state == Cell::Alive as u8).count() as u32;
+        let cells = Cells::Bytes(states);
         let background_cells = cells.clone();
 
         Universe {
@@ -247,42 +534,168 @@ impl Universe {
 
             square_size_px: DEFAULT_SQUARE_SIZE,
             square_spacing_px: DEFAULT_SPACING,
+            state_colors: Vec::new(),
+
+            rule: Rule::default(),
+            topology: Topology::default(),
+
+            tiles: Tiles::new(width, height),
+
+            journal: Journal::new(journal::DEFAULT_MEMORY_LIMIT),
+
+            generation: 0,
+            history: History::new(0, history::DEFAULT_MEMORY_LIMIT),
+
+            stats: Stats::new(population),
+            cycles: Cycles::new(0),
+
+            unpacked_cells: Vec::new(),
+            changed_cells: ChangedCells::new((width * height) as usize),
         }
     }
-}
 
-// Private impl
-impl Universe {
-    fn get_cell_idx(&self, x: u32, y: u32) -> usize {
-        let x = x % self.width;
-        let y = y % self.height;
+    /// Maps ([x], [y]) onto this' surface, returning None if it falls off the edge.
+    fn resolve(&self, x: i64, y: i64) -> Option<(u32, u32)> {
+        self.topology.resolve(x, y, self.width, self.height)
+    }
+
+    /// Replaces the rule, which invalidates what the last tick computed.
+    /// Generations rules switch this to Backend::Bytes, to hold dying states.
+    pub(crate) fn use_rule(&mut self, rule: Rule) {
+        if rule.states() > 2 {
+            self.set_backend(Backend::Bytes);
+        }
 
-        (y * self.width + x) as usize
+        self.rule = rule;
+        self.tiles.mark_all();
+        self.cycles.reset();
     }
 
-    fn get_live_neighbor_count(&self, x: u32, y: u32) -> u32 {
-        let mut count = 0;
+    /// Sets the in-bounds cell at ([x], [y]), marking its tile as changed.
+    /// Isn't recorded for undo (see [edit_cell]), and forgets generation history
+    /// and cycle detection, which only describe ticks.
+    pub(crate) fn write_cell(&mut self, x: u32, y: u32, cell: Cell) {
+        self.write_state(x, y, cell as u8);
+    }
 
-        // Note that everything is modulo self.width or self.height.
-        // As such, x + self.width - 1 \equiv x - 1 (mod self.width),
-        //    but x + self.width - 1 avoids unsigned integer wrapping.
-        for dx in [self.width - 1, 0, 1].iter().cloned() {
-            for dy in [self.height - 1, 0, 1].iter().cloned() {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
+    /// Sets the state of the in-bounds cell at ([x], [y]), as for [write_cell].
+    pub(crate) fn write_state(&mut self, x: u32, y: u32, state: u8) {
+        let before_state = self.cells.get_state(x, y, self.width);
+        let (before, after) = (Cell::from_state(before_state), Cell::from_state(state));
 
-                let x = (x + dx) % self.width;
-                let y = (y + dy) % self.height;
+        self.stats.record_edit(before, after);
+        self.cells.set_state(x, y, self.width, state);
+        self.tiles.mark_cell(x, y);
+        self.history.clear();
+        self.changed_cells.record(y * self.width + x);
 
-                count += match self.get_cell_at(x, y) {
-                    Cell::Dead => 0,
-                    Cell::Alive => 1,
-                };
+        if self.cycles.is_enabled() {
+            self.cycles.record_change(x, y, before_state, state);
+            self.cycles.reset();
+        }
+    }
+
+    /// Sets the in-bounds cell at ([x], [y]), recording the change in the
+    /// open transaction.
+    pub(crate) fn edit_cell(&mut self, x: u32, y: u32, cell: Cell) {
+        let before = self.cells.get_state(x, y, self.width);
+        let after = cell as u8;
+
+        if before != after {
+            self.write_state(x, y, after);
+            self.journal.record(CellEdit { x, y, before, after });
+        }
+    }
+
+    /// Sets all cells to Cell::Dead without recording an undoable edit, which
+    /// forgets earlier edits too.
+    pub(crate) fn reset_cells(&mut self) {
+        let cells = vec![Cell::Dead as u8; (self.width * self.height) as usize];
+
+        self.cells = Cells::new(self.backend(), cells, self.width, self.height);
+        self.tiles.mark_all();
+        self.history.clear();
+        self.journal.clear();
+        self.stats.reset_population(0);
+        self.changed_cells.record_all((self.width * self.height) as usize);
+        self.cycles.rebuild(self.width, self.height, std::iter::empty());
+    }
+
+    /// Writes the next state of the cell at ([x], [y]) into the buffered cells,
+    /// returning true iff it differs from the current state. Births and deaths
+    /// are added to [counts].
+    fn tick_cell(&mut self, x: u32, y: u32, counts: &mut TickCounts) -> bool {
+        let current = self.cells.get_state(x, y, self.width);
+        let next = self.rule.next_cell_state(current, self.get_neighborhood(x, y));
+        self.buffered_cells_.set_state(x, y, self.width, next);
+        counts.record(Cell::from_state(current), Cell::from_state(next));
+
+        next != current
+    }
+
+    /// Passes the cells that differ between the current and buffered (next)
+    /// generation to the generation history, cycle detector and changed cells
+    /// buffer, whichever are enabled, looking only at the tiles flagged in
+    /// [changed].
+    fn record_tick_changes(&mut self, changed: &[bool]) {
+        let (history, cycles, changed_cells) = (self.history.is_enabled(), self.cycles.is_enabled(), self.changed_cells.is_enabled());
+        if !history && !cycles && !changed_cells {
+            return;
+        }
+
+        let mut delta = Vec::new();
+
+        for (x, y, state, next) in self.cells.changes(&self.buffered_cells_, &self.tiles, changed) {
+            let idx = y * self.width + x;
+
+            if history {
+                delta.push((idx, state));
+            }
+            if cycles {
+                self.cycles.record_change(x, y, state, next);
+            }
+            if changed_cells {
+                self.changed_cells.record(idx);
+            }
+        }
+
+        if history {
+            self.history.push(delta);
+        }
+    }
+
+    /// Calls [tick_cell] for each cell on the edges of this, flagging the
+    /// tiles of those that change in [changed]. [counts] are corrected for
+    /// the buffered states these replace.
+    fn tick_edges(&mut self, changed: &mut [bool], counts: &mut TickCounts) {
+        let (width, height) = (self.width, self.height);
+        let top_and_bottom = (0..width).flat_map(|x| [(x, 0), (x, height - 1)]);
+        let sides = (1..height.saturating_sub(1)).flat_map(|y| [(0, y), (width - 1, y)]);
+
+        for (x, y) in top_and_bottom.chain(sides) {
+            counts.unrecord(self.cells.get(x, y, width), self.buffered_cells_.get(x, y, width));
+
+            if self.tick_cell(x, y, counts) {
+                changed[self.tiles.tile_of(x, y)] = true;
+            }
+        }
+    }
+
+    /// Returns the 3x3 neighborhood of the cell at ([x], [y]), as described by
+    /// [Rule::next_cell_state].
+    fn get_neighborhood(&self, x: u32, y: u32) -> u16 {
+        let mut neighborhood = 0;
+        let (x, y) = (i64::from(x), i64::from(y));
+
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if let Some(Cell::Alive) = self.resolve(x + dx, y + dy).map(|(x, y)| self.cells.get(x, y, self.width)) {
+                    neighborhood |= 1 << (3 * (dy + 1) + (dx + 1));
+                }
             }
         }
 
-        count
+        neighborhood
     }
 }
 
@@ -0,0 +1,113 @@
+use std::convert::TryFrom;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Pattern, Universe};
+
+const LIFE_106_HEADER: &str = "#Life 1.06";
+
+impl Pattern {
+    /// Parses a pattern in the Life 1.06 format: a "#Life 1.06" header followed
+    /// by one "x y" line per live cell. Coordinates may be negative, and are
+    /// shifted so that the top-left live cell lands in the top-left of the result.
+    pub fn from_life106(text: &str) -> Result<Pattern, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        if lines.next() != Some(LIFE_106_HEADER) {
+            return Err(format!("Life 1.06 pattern must begin with {:?}", LIFE_106_HEADER));
+        }
+
+        let mut comments = Vec::new();
+        let mut coordinates = Vec::new();
+
+        for line in lines {
+            if let Some(comment) = line.strip_prefix('#') {
+                comments.push(comment.trim().to_string());
+                continue;
+            }
+
+            let mut parts = line.split_whitespace().map(str::parse::<i64>);
+            let point = match (parts.next(), parts.next(), parts.next()) {
+                (Some(Ok(x)), Some(Ok(y)), None) => (x, y),
+                _ => return Err(format!("Invalid Life 1.06 coordinate {:?}", line)),
+            };
+
+            coordinates.push(point);
+        }
+
+        let min_x = coordinates.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let min_y = coordinates.iter().map(|&(_, y)| y).min().unwrap_or(0);
+
+        let offset = |value: i64, min: i64| value.checked_sub(min).and_then(|offset| u32::try_from(offset).ok())
+            .ok_or_else(|| "Life 1.06 pattern is too large".to_string());
+
+        let mut live_cells = Vec::with_capacity(coordinates.len());
+        for (x, y) in coordinates {
+            live_cells.push((offset(x, min_x)?, offset(y, min_y)?));
+        }
+
+        let mut pattern = Pattern::from_live_cells(&live_cells).map_err(|reason| format!("Invalid Life 1.06 pattern: {}", reason))?;
+        pattern.comments = comments;
+
+        Ok(pattern)
+    }
+
+    /// Encodes the live cells of this in the Life 1.06 format.
+    pub fn to_life106(&self) -> String {
+        let mut result = format!("{}\n", LIFE_106_HEADER);
+
+        for (x, y) in self.live_cells() {
+            result.push_str(&format!("{} {}\n", x, y));
+        }
+
+        result
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Loads the Life 1.06 pattern [text] into this with its top-left live
+    /// cell at ([x], [y]).
+    pub fn load_life106(&mut self, text: &str, x: u32, y: u32) -> Result<(), String> {
+        self.paste(&Pattern::from_life106(text)?, x, y);
+        Ok(())
+    }
+
+    /// Creates a universe just large enough to hold the Life 1.06 pattern [text].
+    pub fn from_life106(text: &str) -> Result<Universe, String> {
+        Ok(Universe::from_pattern(&Pattern::from_life106(text)?))
+    }
+
+    /// Encodes the live cells in this in the Life 1.06 format.
+    pub fn to_life106(&self) -> String {
+        self.to_pattern().to_life106()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Pattern;
+
+    #[test]
+    fn round_trips() {
+        let pattern = Pattern::from_life106("#Life 1.06\n#A glider\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+
+        assert_eq!(pattern.comments, vec!["A glider".to_string()]);
+        assert_eq!(pattern.live_cells(), vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        assert_eq!(pattern.to_life106(), "#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n");
+        assert_eq!(Pattern::from_life106(&pattern.to_life106()).unwrap().live_cells(), pattern.live_cells());
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert!(Pattern::from_life106("0 0\n").is_err());
+        assert!(Pattern::from_life106("#Life 1.06\n0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn rejects_oversized() {
+        assert!(Pattern::from_life106("#Life 1.06\n0 0\n4000000000 0\n").is_err());
+        assert!(Pattern::from_life106("#Life 1.06\n-9223372036854775808 0\n9223372036854775807 0\n").is_err());
+        assert!(Pattern::from_life106("#Life 1.06\n0 0\n5000 5000\n").is_err());
+    }
+}
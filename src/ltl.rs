@@ -0,0 +1,263 @@
+use std::fmt;
+
+use crate::stats::TickCounts;
+use crate::{Cell, Universe};
+
+/// Largest neighborhood range accepted in rulestrings.
+pub const MAX_RANGE: u32 = 100;
+
+/// The neighborhood and neighbor count ranges of a Larger than Life rule,
+/// written like "R5,C0,M1,S34..58,B34..45,NM" (Bosco's Rule).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LargerThanLife {
+    /// Cells up to this many cells away horizontally and vertically are neighbors.
+    pub range: u32,
+
+    /// Whether a cell counts itself as a neighbor.
+    pub include_center: bool,
+
+    /// A dead cell comes alive iff its number of live neighbors is in
+    /// birth_min..=birth_max.
+    pub birth_min: u32,
+    pub birth_max: u32,
+
+    /// A live cell stays alive iff its number of live neighbors is in
+    /// survival_min..=survival_max.
+    pub survival_min: u32,
+    pub survival_max: u32,
+}
+
+impl LargerThanLife {
+    /// Parses the comma-separated R, C, M, S, B and N fields of [rulestring],
+    /// returning the parameters and number of states. Errors are reasons,
+    /// without the rulestring.
+    pub fn parse(rulestring: &str) -> Result<(LargerThanLife, u8), String> {
+        let fields: Vec<&str> = rulestring.split(',').map(str::trim).collect();
+
+        let [range, states, center, survival, birth, neighborhood] = fields[..] else {
+            return Err("expected the form R<range>,C<states>,M<0 or 1>,S<min>..<max>,B<min>..<max>,N<neighborhood>".into());
+        };
+
+        let field = |field: &str, prefix: char| -> Result<String, String> {
+            match field.chars().next() {
+                Some(c) if c.eq_ignore_ascii_case(&prefix) => Ok(field[1..].to_string()),
+                _ => Err(format!("expected a field starting with '{}', not {:?}", prefix, field)),
+            }
+        };
+        let number = |digits: &str, what: &str| -> Result<u32, String> {
+            digits.parse().map_err(|_| format!("{:?} is not a {}", digits, what))
+        };
+
+        let range = number(&field(range, 'R')?, "range")?;
+        if !(1..=MAX_RANGE).contains(&range) {
+            return Err(format!("the range must be between 1 and {}, not {}", MAX_RANGE, range));
+        }
+
+        // C0 and C1 are both accepted for two states, as by Golly.
+        let states = match number(&field(states, 'C')?, "number of states")? {
+            0..=2 => 2,
+            states if states <= u32::from(u8::MAX) => states as u8,
+            states => return Err(format!("rules have between 2 and {} states, not {}", u8::MAX, states)),
+        };
+
+        let include_center = match field(center, 'M')?.as_str() {
+            "0" => false,
+            "1" => true,
+            other => return Err(format!("M must be 0 or 1, not {:?}", other)),
+        };
+
+        let max_count = (2 * range + 1) * (2 * range + 1);
+        let count_range = |counts: &str, what: &str| -> Result<(u32, u32), String> {
+            let (min, max) = counts.split_once("..").unwrap_or((counts, counts));
+            let (min, max) = (number(min, what)?, number(max, what)?);
+
+            if min > max || max > max_count {
+                return Err(format!("{}..{} is not a range of neighbor counts within 0..{}", min, max, max_count));
+            }
+
+            Ok((min, max))
+        };
+
+        let (survival_min, survival_max) = count_range(&field(survival, 'S')?, "survival count")?;
+        let (birth_min, birth_max) = count_range(&field(birth, 'B')?, "birth count")?;
+
+        let neighborhood = field(neighborhood, 'N')?;
+        if !neighborhood.eq_ignore_ascii_case("M") {
+            return Err(format!("only the Moore neighborhood (NM) is supported, not N{}", neighborhood));
+        }
+
+        let rule = LargerThanLife { range, include_center, birth_min, birth_max, survival_min, survival_max };
+        Ok((rule, states))
+    }
+
+    pub fn is_born(&self, live_neighbors: u32) -> bool {
+        (self.birth_min..=self.birth_max).contains(&live_neighbors)
+    }
+
+    pub fn survives(&self, live_neighbors: u32) -> bool {
+        (self.survival_min..=self.survival_max).contains(&live_neighbors)
+    }
+
+    /// Formats this as a canonical rulestring, for a rule with [states] states.
+    pub fn fmt(&self, states: u8, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "R{},C{},M{},S{}..{},B{}..{},NM",
+            self.range, if states > 2 { states } else { 0 }, self.include_center as u8,
+            self.survival_min, self.survival_max, self.birth_min, self.birth_max,
+        )
+    }
+}
+
+// Private impl
+impl Universe {
+    /// Writes the next generation under the current Larger than Life rule
+    /// into the buffered cells, flagging the tiles of cells that change in
+    /// [changed] and counting births and deaths in [counts].
+    ///
+    /// Neighbor counts come from a summed-area table over the live cells,
+    /// padded by the range on each side with the cells that the topology maps
+    /// there, so each count takes constant time whatever the range.
+    pub(crate) fn tick_larger_than_life(&mut self, ltl: &LargerThanLife, changed: &mut [bool], counts: &mut TickCounts) {
+        let range = ltl.range as usize;
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (padded_width, padded_height) = (width + 2 * range, height + 2 * range);
+
+        // sums[(y + 1) * stride + x + 1] is the number of live cells in the
+        // padded rows 0..=y and columns 0..=x. Only the padding is mapped
+        // through the topology; the cells of this are read as they are.
+        let states = self.cells.to_vec();
+        let stride = padded_width + 1;
+        let mut sums = vec![0_u32; stride * (padded_height + 1)];
+
+        for py in 0..padded_height {
+            let mut row_sum = 0;
+
+            for px in 0..padded_width {
+                let (x, y) = (px as i64 - range as i64, py as i64 - range as i64);
+
+                let state = if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                    states[y as usize * width + x as usize]
+                } else {
+                    self.resolve(x, y).map_or(Cell::Dead as u8, |(x, y)| states[y as usize * width + x as usize])
+                };
+
+                row_sum += u32::from(state == Cell::Alive as u8);
+                sums[(py + 1) * stride + px + 1] = sums[py * stride + px + 1] + row_sum;
+            }
+        }
+
+        let side = 2 * range + 1;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                // The neighborhood of (x, y) covers padded columns x..x + side
+                // and rows y..y + side.
+                let (left, top) = (x as usize, y as usize);
+                let (right, bottom) = (left + side, top + side);
+                let mut live_neighbors = sums[bottom * stride + right] + sums[top * stride + left]
+                    - sums[top * stride + right] - sums[bottom * stride + left];
+
+                let current = self.cells.get_state(x, y, self.width);
+                if current == Cell::Alive as u8 && !ltl.include_center {
+                    live_neighbors -= 1;
+                }
+
+                let lives = if current == Cell::Alive as u8 { ltl.survives(live_neighbors) } else { ltl.is_born(live_neighbors) };
+                let next = self.rule.step_state(current, lives);
+                self.buffered_cells_.set_state(x, y, self.width, next);
+                counts.record(Cell::from_state(current), Cell::from_state(next));
+
+                if next != current {
+                    changed[self.tiles.tile_of(x, y)] = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LargerThanLife;
+    use crate::testing::states;
+    use crate::{Rule, Topology, Universe};
+
+    /// Computes the next generation of [universe] by counting each cell's
+    /// neighbors one at a time.
+    fn brute_force(universe: &Universe, ltl: &LargerThanLife) -> Vec<u8> {
+        let (width, height) = (universe.width(), universe.height());
+        let range = ltl.range as i64;
+        let current = states(universe);
+
+        (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| {
+            let mut live_neighbors = 0;
+
+            for dy in -range..=range {
+                for dx in -range..=range {
+                    if (dx, dy) == (0, 0) && !ltl.include_center {
+                        continue;
+                    }
+
+                    let neighbor = universe.topology().resolve(i64::from(x) + dx, i64::from(y) + dy, width, height);
+                    if neighbor.is_some_and(|(x, y)| current[(y * width + x) as usize] == 1) {
+                        live_neighbors += 1;
+                    }
+                }
+            }
+
+            let state = current[(y * width + x) as usize];
+            let lives = if state == 1 { ltl.survives(live_neighbors) } else { ltl.is_born(live_neighbors) };
+            universe.rule.step_state(state, lives)
+        }).collect()
+    }
+
+    #[test]
+    fn parses_and_formats() {
+        let bosco = Rule::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(Rule::parse("r2,c3,m0,s2..4,b3,nm").unwrap().to_string(), "R2,C3,M0,S2..4,B3..3,NM");
+
+        for bad in &["R0,C0,M1,S1..2,B1..2,NM", "R5,C0,M2,S1..2,B1..2,NM", "R5,C0,M1,S5..2,B1..2,NM", "R1,C0,M1,S1..10,B1..2,NM", "R5,C0,M1,S1..2"] {
+            assert!(Rule::parse(bad).is_err(), "{}", bad);
+        }
+
+        let von_neumann = Rule::parse("R5,C0,M1,S1..2,B1..2,NN").unwrap_err();
+        assert!(von_neumann.contains("only the Moore neighborhood (NM) is supported"), "{}", von_neumann);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let topologies = [Topology::Torus, Topology::Plane, Topology::Cylinder, Topology::KleinBottle, Topology::CrossSurface];
+
+        for rulestring in &["R5,C0,M1,S34..58,B34..45,NM", "R2,C4,M0,S3..6,B4..5,NM", "R3,C0,M0,S5..12,B6..9,NM"] {
+            for &topology in &topologies {
+                let mut universe = Universe::empty(37, 23);
+                universe.set_topology(topology);
+                universe.set_rule(rulestring).unwrap();
+                universe.randomize(0.45, 11).unwrap();
+
+                let ltl = *universe.rule.larger_than_life().unwrap();
+
+                for generation in 0..8 {
+                    let expected = brute_force(&universe, &ltl);
+                    universe.tick();
+
+                    assert_eq!(states(&universe), expected, "{} {:?} generation {}", rulestring, topology, generation);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn range_1_matches_conway() {
+        let mut ltl = Universe::new(50, 40);
+        let mut conway = Universe::new(50, 40);
+        ltl.set_rule("R1,C0,M0,S2..3,B3..3,NM").unwrap();
+
+        for _ in 0..30 {
+            ltl.tick();
+            conway.tick();
+        }
+
+        assert_eq!(states(&ltl), states(&conway));
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+
+use crate::share::{self, BASE64};
+
+/// Number of bytes in the table of a MAP rule: one bit per 3x3 neighborhood.
+const TABLE_BYTES: usize = 512 / 8;
+
+/// Returns the index of [neighborhood] (as described by
+/// [Rule::next_cell_state]) in the table of a MAP rule, which reads the 3x3
+/// cells from the top-left as the bits of a number, most significant first.
+fn map_index(neighborhood: u16) -> usize {
+    (0..9).filter(|bit| neighborhood & (1 << bit) != 0).fold(0, |idx, bit| idx | 1 << (8 - bit))
+}
+
+/// Parses the base64 that follows "MAP" in a MAP rulestring into a lookup
+/// table, as stored by Rule. Errors are reasons, without the rulestring.
+pub(crate) fn parse(encoded: &str) -> Result<[u64; 8], String> {
+    // The final two characters of padding are optional.
+    let encoded = encoded.strip_suffix("==").unwrap_or(encoded);
+    let bytes = share::decode_base64(encoded, BASE64)?;
+
+    if bytes.len() != TABLE_BYTES {
+        return Err(format!("expected {} bytes of base64 after \"MAP\", not {}", TABLE_BYTES, bytes.len()));
+    }
+
+    let mut table = [0; 8];
+    for neighborhood in 0..512_u16 {
+        let idx = map_index(neighborhood);
+
+        // The first neighborhood of each byte is its most significant bit.
+        if bytes[idx / 8] & (0x80 >> (idx % 8)) != 0 {
+            table[usize::from(neighborhood / 64)] |= 1 << (neighborhood % 64);
+        }
+    }
+
+    Ok(table)
+}
+
+/// Formats the lookup [table] as a MAP rulestring, without padding.
+pub(crate) fn fmt(table: &[u64; 8], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut bytes = [0_u8; TABLE_BYTES];
+
+    for neighborhood in 0..512_u16 {
+        if table[usize::from(neighborhood / 64)] & (1 << (neighborhood % 64)) != 0 {
+            let idx = map_index(neighborhood);
+            bytes[idx / 8] |= 0x80 >> (idx % 8);
+        }
+    }
+
+    write!(f, "MAP{}", share::encode_base64(&bytes, BASE64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TABLE_BYTES;
+    use crate::share::{encode_base64, BASE64};
+    use crate::testing::live_cells;
+    use crate::{Rule, Universe};
+
+    const CONWAY: &str = "MAPARYXfhZofugWaH7oaIDogBZofuhogOiAaIDogIAAgAAWaH7oaIDogGiA6ICAAIAAaIDogIAAgACAAIAAAAAAAA";
+
+    #[test]
+    fn parses_conway() {
+        assert_eq!(Rule::parse(CONWAY).unwrap(), Rule::CONWAY);
+        assert_eq!(Rule::parse(&format!("{}==", CONWAY)).unwrap(), Rule::CONWAY);
+        assert_eq!(Rule::parse(&CONWAY.replace("MAP", "map")).unwrap(), Rule::CONWAY);
+        assert_eq!(Rule::parse(CONWAY).unwrap().to_string(), "B3/S23");
+    }
+
+    #[test]
+    fn round_trips_anisotropic_rules() {
+        // A cell comes alive iff only its north neighbor is alive, which is
+        // bit 7 of the MAP index (NW is the most significant of 9 bits).
+        let mut bytes = [0_u8; TABLE_BYTES];
+        bytes[0b010_000_000 / 8] = 0x80;
+        let rulestring = format!("MAP{}", encode_base64(&bytes, BASE64));
+
+        let rule = Rule::parse(&rulestring).unwrap();
+        assert_eq!(rule.to_string(), rulestring);
+
+        // So a lone cell moves south one cell per generation.
+        let mut universe = Universe::empty(5, 5);
+        universe.set_rule(&rulestring).unwrap();
+        universe.load_rle("x = 1, y = 1\no!", 2, 0).unwrap();
+
+        universe.tick();
+        assert_eq!(live_cells(&universe), vec![(2, 1)]);
+        universe.tick();
+        assert_eq!(live_cells(&universe), vec![(2, 2)]);
+    }
+
+    #[test]
+    fn rejects_invalid_tables() {
+        for rulestring in &["MAP", "MAPARYX", &CONWAY[..CONWAY.len() - 4], &format!("{}AAAA", CONWAY), &CONWAY.replace('f', "!")] {
+            assert!(Rule::parse(rulestring).is_err(), "{}", rulestring);
+        }
+    }
+}
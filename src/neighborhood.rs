@@ -0,0 +1,199 @@
+use std::f64::consts::PI;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Universe};
+
+/// The cells that count as neighbors under a rule over the nearest cells,
+/// selected by the suffix of its rulestring.
+#[wasm_bindgen]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Neighborhood {
+    /// The 8 surrounding cells. No suffix.
+    #[default]
+    Moore = 0,
+
+    /// The 4 orthogonally adjacent cells. Suffix 'V'.
+    VonNeumann = 1,
+
+    /// The 6 cells around a hexagon, emulated on the square grid by ignoring
+    /// the north-east and south-west neighbors. Suffix 'H'.
+    Hexagonal = 2,
+}
+
+impl Neighborhood {
+    pub const ALL: [Neighborhood; 3] = [Neighborhood::Moore, Neighborhood::VonNeumann, Neighborhood::Hexagonal];
+
+    /// Returns the cells of a 3x3 neighborhood (as described by
+    /// [Rule::next_cell_state]) that are neighbors.
+    pub const fn neighbors(self) -> u16 {
+        match self {
+            Neighborhood::Moore => 0b111_101_111,
+            Neighborhood::VonNeumann => 0b010_101_010,
+            Neighborhood::Hexagonal => 0b110_101_011,
+        }
+    }
+
+    /// Returns the suffix of rulestrings over this.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Neighborhood::Moore => "",
+            Neighborhood::VonNeumann => "V",
+            Neighborhood::Hexagonal => "H",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Neighborhood::Moore => "Moore",
+            Neighborhood::VonNeumann => "von Neumann",
+            Neighborhood::Hexagonal => "hexagonal",
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Returns the neighborhood of the current rule.
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.rule.neighborhood()
+    }
+
+    /// Renders cells as hexagons using paths, for rules over the hexagonal
+    /// neighborhood. See [fill_hex_state].
+    pub fn fill_hex_cells(&self, cell_type: Cell, ctx: &web_sys::CanvasRenderingContext2d) {
+        self.fill_hex_state(cell_type as u8, ctx);
+    }
+
+    /// Renders the cells in [state] as hexagons the square size across, with
+    /// the square spacing between them.
+    ///
+    /// Each row is drawn half a cell left of the row above, so that each cell
+    /// touches the six cells that are its hexagonal neighbors: those to the
+    /// north-west, north, west, east, south and south-east. The cells take up
+    /// [hex_layout_width] x [hex_layout_height] pixels.
+    pub fn fill_hex_state(&self, state: u8, ctx: &web_sys::CanvasRenderingContext2d) {
+        // The distance from the center of a hexagon to each of its corners.
+        let radius = f64::from(self.square_size_px) / 3_f64.sqrt();
+
+        ctx.begin_path();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.get_state_at(x, y) != state {
+                    continue;
+                }
+
+                let (center_x, center_y) = self.hex_center(x, y);
+
+                // Corners start at the lower right, with a corner at the top
+                // and bottom of each hexagon.
+                for corner in 0..6 {
+                    let angle = PI / 6.0 + PI / 3.0 * f64::from(corner);
+                    let (corner_x, corner_y) = (center_x + radius * angle.cos(), center_y + radius * angle.sin());
+
+                    if corner == 0 {
+                        ctx.move_to(corner_x, corner_y);
+                    } else {
+                        ctx.line_to(corner_x, corner_y);
+                    }
+                }
+
+                ctx.close_path();
+            }
+        }
+
+        ctx.fill();
+    }
+
+    /// Returns the width in pixels of the cells drawn by [fill_hex_state].
+    pub fn hex_layout_width(&self) -> f64 {
+        let pitch = f64::from(self.square_size_px + self.square_spacing_px);
+
+        (f64::from(self.width) + f64::from(self.height.saturating_sub(1)) / 2.0) * pitch + f64::from(self.square_spacing_px)
+    }
+
+    /// Returns the height in pixels of the cells drawn by [fill_hex_state].
+    pub fn hex_layout_height(&self) -> f64 {
+        let (_, last_center_y) = self.hex_center(0, self.height.saturating_sub(1));
+
+        last_center_y + f64::from(self.square_size_px) / 3_f64.sqrt() + f64::from(self.square_spacing_px)
+    }
+}
+
+// Private impl
+impl Universe {
+    /// Returns the center, in pixels, of the hexagon drawn for the cell at
+    /// ([x], [y]) by [fill_hex_state].
+    fn hex_center(&self, x: u32, y: u32) -> (f64, f64) {
+        let pitch = f64::from(self.square_size_px + self.square_spacing_px);
+        let (spacing, size) = (f64::from(self.square_spacing_px), f64::from(self.square_size_px));
+
+        // Rows of touching hexagons are sqrt(3) / 2 of their width apart.
+        let shift = f64::from(self.height.saturating_sub(1) - y) / 2.0;
+        let center_x = spacing + size / 2.0 + (f64::from(x) + shift) * pitch;
+        let center_y = spacing + size / 3_f64.sqrt() + f64::from(y) * pitch * 3_f64.sqrt() / 2.0;
+
+        (center_x, center_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Neighborhood;
+    use crate::testing::live_cells;
+    use crate::{Rule, Topology, Universe};
+
+    /// Ticks the [live_cells] of a small plane once under [rulestring].
+    fn tick(rulestring: &str, live: &[(u32, u32)]) -> Vec<(u32, u32)> {
+        let mut universe = Universe::empty(6, 6);
+        universe.set_topology(Topology::Plane);
+        universe.set_rule(rulestring).unwrap();
+
+        for &(x, y) in live {
+            universe.toggle_cell_at(x, y);
+        }
+
+        universe.tick();
+        live_cells(&universe)
+    }
+
+    #[test]
+    fn parses_suffixes() {
+        for (rulestring, neighbors) in [("B2/S34H", Neighborhood::Hexagonal), ("b1/s1v", Neighborhood::VonNeumann), ("B3/S23", Neighborhood::Moore)] {
+            let rule = Rule::parse(rulestring).unwrap();
+
+            assert_eq!(rule.neighborhood(), neighbors);
+            assert_eq!(rule.to_string(), rulestring.to_uppercase());
+        }
+
+        assert!(Rule::parse("B5/S1V").is_err());
+        assert!(Rule::parse("B2/S7H").is_err());
+        assert!(Rule::parse("B2a/S1V").is_err());
+    }
+
+    #[test]
+    fn counts_von_neumann_neighbors() {
+        // The two dead cells orthogonally adjacent to both live ones come
+        // alive; the live ones have no orthogonal neighbors and die.
+        assert_eq!(tick("B2/SV", &[(2, 2), (3, 3)]), vec![(3, 2), (2, 3)]);
+        assert_eq!(tick("B2/S", &[(2, 2), (3, 3)]), vec![(3, 2), (2, 3)]);
+
+        // With Moore neighbors, the diagonal pair keeps each other alive.
+        assert_eq!(tick("B/S1", &[(2, 2), (3, 3)]), vec![(2, 2), (3, 3)]);
+        assert_eq!(tick("B/S1V", &[(2, 2), (3, 3)]), vec![]);
+    }
+
+    #[test]
+    fn counts_hexagonal_neighbors() {
+        // Of the cells next to both (2, 2) and (3, 2), only those to the north
+        // of the left and south of the right are hexagonal neighbors of both.
+        assert_eq!(tick("B2/S2H", &[(2, 2), (3, 2)]), vec![(2, 1), (3, 3)]);
+        assert_eq!(tick("B2/S2", &[(2, 2), (3, 2)]), vec![(2, 1), (3, 1), (2, 3), (3, 3)]);
+
+        // North-east and south-west aren't hexagonal neighbors.
+        assert_eq!(tick("B/S1H", &[(2, 2), (3, 1)]), vec![]);
+        assert_eq!(tick("B/S1H", &[(2, 2), (3, 3)]), vec![(2, 2), (3, 3)]);
+    }
+}
@@ -0,0 +1,264 @@
+use std::convert::TryFrom;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Rule, Universe};
+
+/// The largest width or height of a pattern read from text or bytes, which
+/// could otherwise claim any size.
+pub(crate) const MAX_DIMENSION: u32 = 1 << 16;
+
+/// The most cells a pattern read from text or bytes may have (at one byte
+/// each), so that a hostile header can't exhaust memory.
+pub(crate) const MAX_CELL_COUNT: usize = 1 << 24;
+
+/// Returns the number of cells in a [width] x [height] grid read from text or
+/// bytes, unless it exceeds MAX_DIMENSION or MAX_CELL_COUNT.
+pub(crate) fn checked_cell_count(width: u32, height: u32) -> Result<usize, String> {
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(format!("{} x {} is larger than the maximum of {} cells across", width, height, MAX_DIMENSION));
+    }
+
+    (width as usize).checked_mul(height as usize).filter(|&count| count <= MAX_CELL_COUNT)
+        .ok_or_else(|| format!("{} x {} is more than the maximum of {} cells", width, height, MAX_CELL_COUNT))
+}
+
+/// A rectangle of cells, along with the metadata that pattern files
+/// attach to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    width: u32,
+    height: u32,
+
+    /// Row-major, [width] * [height] cells.
+    cells: Vec<Cell>,
+
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+    pub rule: Option<Rule>,
+}
+
+impl Pattern {
+    /// Creates a [width] x [height] pattern of dead cells.
+    pub fn new(width: u32, height: u32) -> Pattern {
+        Pattern {
+            width,
+            height,
+            cells: vec![Cell::Dead; (width as usize).checked_mul(height as usize).expect("pattern is too large")],
+
+            name: None,
+            comments: Vec::new(),
+            rule: None,
+        }
+    }
+
+    /// Creates the smallest pattern containing each of [live_cells], unless it
+    /// would be too large to have been read from text.
+    pub fn from_live_cells(live_cells: &[(u32, u32)]) -> Result<Pattern, String> {
+        let width = live_cells.iter().map(|&(x, _)| u64::from(x) + 1).max().unwrap_or(0);
+        let height = live_cells.iter().map(|&(_, y)| u64::from(y) + 1).max().unwrap_or(0);
+
+        let too_large = || format!("a pattern spanning {} x {} cells is too large", width, height);
+        let width = u32::try_from(width).map_err(|_| too_large())?;
+        let height = u32::try_from(height).map_err(|_| too_large())?;
+        checked_cell_count(width, height)?;
+
+        let mut pattern = Pattern::new(width, height);
+        for &(x, y) in live_cells {
+            pattern.set_cell_at(x, y, Cell::Alive);
+        }
+
+        Ok(pattern)
+    }
+
+    pub fn width(&self) -> u32 { self.width }
+    pub fn height(&self) -> u32 { self.height }
+
+    /// Returns the cell at ([x], [y]), or Cell::Dead if it is outside of this.
+    pub fn get_cell_at(&self, x: u32, y: u32) -> Cell {
+        if x < self.width && y < self.height {
+            self.cells[self.index(x, y)]
+        } else {
+            Cell::Dead
+        }
+    }
+
+    /// Sets the cell at ([x], [y]), growing this if it is outside of it. Each
+    /// growth copies every cell, so size this first when setting many cells.
+    pub fn set_cell_at(&mut self, x: u32, y: u32, cell: Cell) {
+        if x >= self.width || y >= self.height {
+            if cell == Cell::Dead {
+                return;
+            }
+
+            self.resize(self.width.max(x + 1), self.height.max(y + 1));
+        }
+
+        let idx = self.index(x, y);
+        self.cells[idx] = cell;
+    }
+
+    /// Changes the size of this, keeping cells in the overlapping region.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let mut resized = Pattern::new(width, height);
+        for y in 0..height.min(self.height) {
+            for x in 0..width.min(self.width) {
+                let idx = resized.index(x, y);
+                resized.cells[idx] = self.get_cell_at(x, y);
+            }
+        }
+
+        self.width = width;
+        self.height = height;
+        self.cells = resized.cells;
+    }
+
+    /// Returns the index in [cells] of the cell at ([x], [y]).
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Parses [text] as an RLE, Life 1.06 or plaintext pattern, or an apgcode,
+    /// depending on which format it looks like.
+    pub fn parse(text: &str) -> Result<Pattern, String> {
+        let trimmed = text.trim_start();
+        let first_line = trimmed.lines().next().unwrap_or("").trim();
+        let first_content_line = trimmed.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('#'));
+
+        if first_line.starts_with("#Life 1.06") {
+            Pattern::from_life106(text)
+        } else if first_content_line.is_some_and(|line| line.starts_with('x') && line.contains('=')) {
+            Pattern::from_rle(text)
+        } else if ["xs", "xp", "xq"].iter().any(|prefix| first_line.starts_with(prefix)) && first_line.contains('_') {
+            Pattern::from_apgcode(first_line)
+        } else {
+            Pattern::from_plaintext(text)
+        }
+    }
+
+    /// Returns the coordinates of all live cells in this, in row-major order.
+    pub fn live_cells(&self) -> Vec<(u32, u32)> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.get_cell_at(x, y) != Cell::Dead)
+            .collect()
+    }
+}
+
+impl Universe {
+    /// Creates a universe just large enough to hold [pattern], using its rule
+    /// if it has one. Loading [pattern] isn't an edit, so it can't be undone.
+    pub fn from_pattern(pattern: &Pattern) -> Universe {
+        let (width, height) = (pattern.width().max(1), pattern.height().max(1));
+        let states = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| pattern.get_cell_at(x, y) as u8)
+            .collect();
+
+        let mut universe = Universe::with_states(width, height, states);
+        universe.use_rule(pattern.rule.unwrap_or_default());
+
+        universe
+    }
+
+    /// Copies [pattern] into this with its top-left corner at ([x], [y]),
+    /// overwriting all cells in the rectangle it covers. Cells beyond the edges
+    /// of this are mapped according to its topology.
+    pub fn paste(&mut self, pattern: &Pattern, x: u32, y: u32) {
+        self.journal.begin();
+
+        for py in 0..pattern.height() {
+            for px in 0..pattern.width() {
+                let point = self.resolve(i64::from(x) + i64::from(px), i64::from(y) + i64::from(py));
+
+                if let Some((x, y)) = point {
+                    self.edit_cell(x, y, pattern.get_cell_at(px, py));
+                }
+            }
+        }
+
+        self.journal.end();
+    }
+
+    /// Copies the [width] x [height] region of this with its top-left corner at
+    /// ([x], [y]) into a new pattern. The region must be within the edges of
+    /// this, and no larger than a pattern read from text.
+    pub fn region_to_pattern(&self, x: u32, y: u32, width: u32, height: u32) -> Result<Pattern, String> {
+        let within = |start: u32, length: u32, edge: u32| start.checked_add(length).is_some_and(|end| end <= edge);
+
+        if !within(x, width, self.width) || !within(y, height, self.height) {
+            return Err(format!(
+                "The {} x {} region at ({}, {}) extends beyond the {} x {} universe",
+                width, height, x, y, self.width, self.height,
+            ));
+        }
+
+        checked_cell_count(width, height)?;
+        Ok(self.copy_region(x, y, width, height))
+    }
+
+    /// Copies all cells in this into a new pattern.
+    pub fn to_pattern(&self) -> Pattern {
+        self.copy_region(0, 0, self.width, self.height)
+    }
+
+    /// Copies the [width] x [height] region of this with its top-left corner at
+    /// ([x], [y]), which must be within the edges of this, into a new pattern.
+    fn copy_region(&self, x: u32, y: u32, width: u32, height: u32) -> Pattern {
+        let mut pattern = Pattern::new(width, height);
+        pattern.rule = Some(self.rule);
+
+        for py in 0..height {
+            for px in 0..width {
+                pattern.set_cell_at(px, py, self.cells.get(x + px, y + py, self.width));
+            }
+        }
+
+        pattern
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Creates a universe just large enough to hold the pattern [text], which
+    /// can be in any format that [Pattern::parse] understands.
+    pub fn from_pattern_string(text: &str) -> Result<Universe, String> {
+        Ok(Universe::from_pattern(&Pattern::parse(text)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MAX_DIMENSION;
+    use crate::{Cell, Universe};
+
+    #[test]
+    fn copies_regions() {
+        let universe = Universe::from_rle("x = 4, y = 3\nbo$2bo$3o!").unwrap();
+        let region = universe.region_to_pattern(1, 1, 3, 2).unwrap();
+
+        assert_eq!(region.live_cells(), vec![(1, 0), (0, 1), (1, 1)]);
+        assert_eq!(universe.region_to_pattern(0, 0, 4, 3).unwrap(), universe.to_pattern());
+        assert_eq!(universe.region_to_pattern(4, 3, 0, 0).unwrap().live_cells(), vec![]);
+    }
+
+    #[test]
+    fn rejects_regions_beyond_edges() {
+        let mut universe = Universe::empty(8, 8);
+        universe.set_cell_at(7, 7, Cell::Alive);
+
+        for &(x, y, width, height) in &[(u32::MAX - 1, 0, 5, 1), (0, u32::MAX, 1, 1), (4, 0, 5, 1), (0, 0, u32::MAX, u32::MAX), (0, 9, 0, 0)] {
+            assert!(universe.region_to_pattern(x, y, width, height).is_err(), "{} {} {} {}", x, y, width, height);
+            assert!(universe.region_to_url_code(x, y, width, height).is_err());
+            assert!(universe.region_to_apgcode(x, y, width, height).is_err());
+        }
+    }
+
+    #[test]
+    fn caps_region_size() {
+        let universe = Universe::empty(MAX_DIMENSION + 1, 1);
+
+        assert!(universe.region_to_pattern(0, 0, MAX_DIMENSION + 1, 1).is_err());
+        assert_eq!(universe.region_to_pattern(1, 0, MAX_DIMENSION, 1).unwrap().width(), MAX_DIMENSION);
+    }
+}
@@ -0,0 +1,125 @@
+use std::convert::TryFrom;
+
+use wasm_bindgen::prelude::*;
+
+use crate::pattern::checked_cell_count;
+use crate::{Cell, Pattern, Universe};
+
+impl Pattern {
+    /// Parses a pattern in the LifeWiki plaintext (.cells) format, e.g.
+    ///   !Name: Glider
+    ///   .O
+    ///   ..O
+    ///   OOO
+    pub fn from_plaintext(text: &str) -> Result<Pattern, String> {
+        let mut pattern = Pattern::new(0, 0);
+        let mut rows = Vec::new();
+
+        for line in text.lines() {
+            if let Some(comment) = line.strip_prefix('!') {
+                let comment = comment.trim();
+
+                match comment.strip_prefix("Name:") {
+                    Some(name) => pattern.name = Some(name.trim().to_string()),
+                    None => pattern.comments.push(comment.to_string()),
+                }
+
+                continue;
+            }
+
+            let row = line.trim_end();
+            if let Some(c) = row.chars().find(|&c| !matches!(c, '.' | 'O' | '*')) {
+                return Err(format!("Unexpected character {:?} on line {} of plaintext pattern", c, rows.len() + 1));
+            }
+
+            rows.push(row);
+        }
+
+        // Trailing lines of dead cells are significant, but trailing dead cells
+        // within lines aren't.
+        let width = rows.iter().map(|row| row.rfind(|c| c != '.').map_or(0, |x| x + 1)).max().unwrap_or(0);
+        let width = u32::try_from(width).unwrap_or(u32::MAX);
+        let height = u32::try_from(rows.len()).unwrap_or(u32::MAX);
+        checked_cell_count(width, height).map_err(|reason| format!("Plaintext pattern is too large: {}", reason))?;
+
+        pattern.resize(width, height);
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, _) in row.chars().enumerate().filter(|&(_, c)| c != '.') {
+                pattern.set_cell_at(x as u32, y as u32, Cell::Alive);
+            }
+        }
+
+        Ok(pattern)
+    }
+
+    /// Encodes this in the plaintext format, with one line per row.
+    pub fn to_plaintext(&self) -> String {
+        let mut result = String::new();
+
+        if let Some(name) = &self.name {
+            result.push_str(&format!("!Name: {}\n", name));
+        }
+
+        for comment in &self.comments {
+            result.push_str(&format!("!{}\n", comment));
+        }
+
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                result.push(if self.get_cell_at(x, y) == Cell::Dead { '.' } else { 'O' });
+            }
+
+            result.push('\n');
+        }
+
+        result
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Loads the plaintext pattern [text] into this with its top-left corner
+    /// at ([x], [y]).
+    pub fn load_plaintext(&mut self, text: &str, x: u32, y: u32) -> Result<(), String> {
+        self.paste(&Pattern::from_plaintext(text)?, x, y);
+        Ok(())
+    }
+
+    /// Creates a universe just large enough to hold the plaintext pattern [text].
+    pub fn from_plaintext(text: &str) -> Result<Universe, String> {
+        Ok(Universe::from_pattern(&Pattern::from_plaintext(text)?))
+    }
+
+    /// Encodes all cells in this in the plaintext format.
+    pub fn to_plaintext(&self) -> String {
+        self.to_pattern().to_plaintext()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Pattern;
+
+    #[test]
+    fn round_trips() {
+        let text = "!Name: Glider\n!A small ship\n.O.\n..O\nOOO\n...\n";
+        let pattern = Pattern::from_plaintext(text).unwrap();
+
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!((pattern.width(), pattern.height()), (3, 4));
+        assert_eq!(pattern.live_cells(), vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        assert_eq!(pattern.to_plaintext(), text);
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert!(Pattern::from_plaintext(".O.\n.X.\n").is_err());
+    }
+
+    #[test]
+    fn rejects_oversized() {
+        let text = format!("{}O\n{}", ".".repeat(70_000), "\n".repeat(10));
+        assert!(Pattern::from_plaintext(&text).is_err());
+    }
+}
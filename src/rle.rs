@@ -0,0 +1,302 @@
+use wasm_bindgen::prelude::*;
+
+use crate::pattern::{checked_cell_count, MAX_DIMENSION};
+use crate::{Cell, Pattern, Rule, Universe};
+
+/// Maximum length of a line in RLE output, per the format's convention.
+const MAX_LINE_LENGTH: usize = 70;
+
+impl Pattern {
+    /// Parses a pattern in the run length encoded format used by Golly and
+    /// the LifeWiki, e.g.
+    ///   #N Glider
+    ///   x = 3, y = 3, rule = B3/S23
+    ///   bo$2bo$3o!
+    pub fn from_rle(rle: &str) -> Result<Pattern, String> {
+        let mut pattern = Pattern::new(0, 0);
+        let mut lines = rle.lines();
+
+        // Comments, then the header.
+        let header = loop {
+            let line = match lines.next() {
+                Some(line) => line.trim(),
+                None => return Err("RLE is missing its \"x = .., y = ..\" header line".into()),
+            };
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(comment) = line.strip_prefix('#') {
+                let mut chars = comment.chars();
+                let kind = chars.next();
+                let text = chars.as_str().trim();
+
+                match kind {
+                    Some('N') => pattern.name = Some(text.to_string()),
+                    Some('C') | Some('c') | Some('O') => pattern.comments.push(text.to_string()),
+                    Some('r') => pattern.rule = Some(Rule::parse(text)?),
+                    _ => {},
+                }
+
+                continue;
+            }
+
+            break Self::parse_rle_header(line, &mut pattern)?;
+        };
+
+        let mut x = 0;
+        let mut y = 0;
+        let mut count: Option<u32> = None;
+
+        // Runs of live cells, as (x, y, length), so that the cells are only
+        // allocated once the size of the pattern is known.
+        let mut live_runs = Vec::new();
+
+        for c in lines.flat_map(|line| line.chars()) {
+            if let Some(digit) = c.to_digit(10) {
+                count = Some(count.unwrap_or(0).checked_mul(10)
+                        .and_then(|count| count.checked_add(digit))
+                        .filter(|&count| count <= MAX_DIMENSION)
+                        .ok_or("RLE run count is too large")?);
+                continue;
+            }
+
+            let run = count.take().unwrap_or(1);
+
+            match c {
+                'b' | '.' => x += run,
+                'o' | 'A' => {
+                    live_runs.push((x, y, run));
+                    x += run;
+                },
+                '$' => {
+                    x = 0;
+                    y += run;
+                },
+                '!' => break,
+                c if c.is_whitespace() => {},
+                c => return Err(format!("Unexpected character {:?} in RLE", c)),
+            }
+
+            if x > MAX_DIMENSION || y > MAX_DIMENSION {
+                return Err(format!("RLE pattern is larger than the maximum of {} cells across", MAX_DIMENSION));
+            }
+        }
+
+        let (mut width, mut height) = header;
+        for &(x, y, run) in &live_runs {
+            width = width.max(x + run);
+            height = height.max(y + 1);
+        }
+
+        checked_cell_count(width, height).map_err(|reason| format!("RLE pattern is too large: {}", reason))?;
+        pattern.resize(width, height);
+
+        for (x, y, run) in live_runs {
+            for x in x..x + run {
+                pattern.set_cell_at(x, y, Cell::Alive);
+            }
+        }
+
+        Ok(pattern)
+    }
+
+    /// Encodes this in the run length encoded format, wrapping lines at
+    /// 70 columns.
+    pub fn to_rle(&self) -> String {
+        let mut result = String::new();
+
+        if let Some(name) = &self.name {
+            result.push_str(&format!("#N {}\n", name));
+        }
+
+        for comment in &self.comments {
+            result.push_str(&format!("#C {}\n", comment));
+        }
+
+        result.push_str(&format!("x = {}, y = {}", self.width(), self.height()));
+        if let Some(rule) = &self.rule {
+            result.push_str(&format!(", rule = {}", rule));
+        }
+        result.push('\n');
+
+        let mut line = String::new();
+        let mut push_item = |line: &mut String, run: u32, tag: char| {
+            let item = if run == 1 { tag.to_string() } else { format!("{}{}", run, tag) };
+
+            if line.len() + item.len() > MAX_LINE_LENGTH {
+                result.push_str(line);
+                result.push('\n');
+                line.clear();
+            }
+
+            line.push_str(&item);
+        };
+
+        // Row ends are only written once the next non-empty row is reached,
+        // so that consecutive empty rows collapse into a single "n$" item.
+        let mut pending_rows = 0;
+
+        for y in 0..self.height() {
+            let mut x = 0;
+
+            while x < self.width() {
+                let cell = self.get_cell_at(x, y);
+                let run = (x..self.width()).take_while(|&i| self.get_cell_at(i, y) == cell).count() as u32;
+
+                // Dead cells at the end of a row are implied.
+                if cell == Cell::Dead && x + run == self.width() {
+                    break;
+                }
+
+                if pending_rows > 0 {
+                    push_item(&mut line, pending_rows, '$');
+                    pending_rows = 0;
+                }
+
+                push_item(&mut line, run, if cell == Cell::Dead { 'b' } else { 'o' });
+                x += run;
+            }
+
+            pending_rows += 1;
+        }
+
+        push_item(&mut line, 1, '!');
+        result.push_str(&line);
+        result.push('\n');
+
+        result
+    }
+
+    /// Parses the "x = .., y = .., rule = .." header [line] into the rule of
+    /// [pattern], returning the width and height it gives.
+    fn parse_rle_header(line: &str, pattern: &mut Pattern) -> Result<(u32, u32), String> {
+        let mut width = None;
+        let mut height = None;
+
+        // The rule is always last, and its bounded grid suffix may contain commas.
+        let (sizes, rule) = match line.find("rule") {
+            Some(idx) => line.split_at(idx),
+            None => (line, ""),
+        };
+
+        for entry in sizes.split(',').chain(std::iter::once(rule)) {
+            if entry.trim().is_empty() {
+                continue;
+            }
+
+            let mut parts = entry.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next()
+                .ok_or_else(|| format!("Malformed RLE header entry {:?}", entry.trim()))?
+                .trim();
+
+            let parse_size = |value: &str| {
+                value.parse::<u32>().map_err(|_| format!("Invalid pattern size {:?} in RLE header", value))
+            };
+
+            match key {
+                "x" => width = Some(parse_size(value)?),
+                "y" => height = Some(parse_size(value)?),
+
+                // Golly appends bounded grid information (e.g. ":T64,64") to the rule,
+                // which doesn't apply to our universes.
+                "rule" => pattern.rule = Some(Rule::parse(value.split(':').next().unwrap_or(""))?),
+                _ => {},
+            }
+        }
+
+        match (width, height) {
+            (Some(width), Some(height)) => {
+                checked_cell_count(width, height).map_err(|reason| format!("Invalid pattern size in RLE header: {}", reason))?;
+                Ok((width, height))
+            },
+            _ => Err(format!("RLE header {:?} is missing x or y", line)),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Loads the RLE-encoded pattern [rle] into this with its top-left corner
+    /// at ([x], [y]). If [rle] specifies a rule, this switches to that rule.
+    pub fn load_rle(&mut self, rle: &str, x: u32, y: u32) -> Result<(), String> {
+        let pattern = Pattern::from_rle(rle)?;
+
+        if let Some(rule) = pattern.rule {
+            self.use_rule(rule);
+        }
+
+        self.paste(&pattern, x, y);
+        Ok(())
+    }
+
+    /// Creates a universe just large enough to hold the RLE-encoded pattern [rle].
+    pub fn from_rle(rle: &str) -> Result<Universe, String> {
+        Ok(Universe::from_pattern(&Pattern::from_rle(rle)?))
+    }
+
+    /// Encodes all cells in this as RLE, including the current rule.
+    pub fn to_rle(&self) -> String {
+        self.to_pattern().to_rle()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cell, Pattern};
+
+    const GLIDER: &str = "#N Glider\n#C A small ship\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+
+    const GOSPER_GLIDER_GUN: &str = "#N Gosper glider gun
+x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!
+";
+
+    #[test]
+    fn parses_glider() {
+        let pattern = Pattern::from_rle(GLIDER).unwrap();
+
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments, vec!["A small ship".to_string()]);
+        assert_eq!(pattern.live_cells(), vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn round_trips() {
+        for rle in &[GLIDER, GOSPER_GLIDER_GUN] {
+            let pattern = Pattern::from_rle(rle).unwrap();
+
+            assert_eq!(pattern.to_rle(), *rle);
+            assert_eq!(Pattern::from_rle(&pattern.to_rle()).unwrap(), pattern);
+        }
+    }
+
+    #[test]
+    fn grows_to_fit_cells_beyond_header() {
+        let pattern = Pattern::from_rle("x = 0, y = 0, rule = B3/S23:T64,64\n3o3$o!").unwrap();
+
+        assert_eq!((pattern.width(), pattern.height()), (3, 4));
+        assert_eq!(pattern.live_cells(), vec![(0, 0), (1, 0), (2, 0), (0, 3)]);
+        assert_eq!(pattern.get_cell_at(2, 3), Cell::Dead);
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert!(Pattern::from_rle("bo$!").is_err());
+        assert!(Pattern::from_rle("x = 3, y = 3\nbqo!").is_err());
+        assert!(Pattern::from_rle("x = 3\nbo!").is_err());
+    }
+
+    #[test]
+    fn rejects_oversized() {
+        assert!(Pattern::from_rle("x = 4000000000, y = 1\no!").is_err());
+        assert!(Pattern::from_rle("x = 65536, y = 65536\no!").is_err());
+        assert!(Pattern::from_rle("x = 1, y = 1\n999999999o!").is_err());
+        assert!(Pattern::from_rle("x = 1, y = 1\n99999999999999999999o!").is_err());
+        assert!(Pattern::from_rle("x = 1, y = 1\n60000$60000bo!").is_err());
+        assert!(Pattern::from_rle(&format!("x = 1, y = 1\n{}o!", "65536b".repeat(2))).is_err());
+    }
+}
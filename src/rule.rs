@@ -0,0 +1,471 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::hensel::Isotropic;
+use crate::ltl::LargerThanLife;
+use crate::{map, Cell, Neighborhood};
+
+/// The bit of the cell itself in a 3x3 neighborhood, as passed to
+/// [Rule::next_cell_state].
+pub(crate) const CENTER: u16 = 1 << 4;
+
+/// An outer-totalistic rule over the Moore neighborhood, written as a B/S
+/// rulestring (e.g. "B3/S23" for Conway's Life).
+///
+/// Isotropic non-totalistic rules (e.g. "B2-a/S12") also depend on how the
+/// live neighbors are arranged, written in Hensel notation, and MAP rules list
+/// the next state of every 3x3 neighborhood in base64. All of these compile
+/// to the table of a MAP rule.
+///
+/// Outer-totalistic rules may instead count the von Neumann or hexagonal
+/// neighborhood, as in "B2/S34H".
+///
+/// Generations rules (e.g. "B2/S/C3" for Brian's Brain) have more than two
+/// states: live cells that don't survive pass through dying states 2, 3, ...,
+/// up to [states] - 1, one per generation, before they die. Dying cells don't
+/// count as live neighbors, and can't come alive.
+///
+/// Larger than Life rules (e.g. "R5,C0,M1,S34..58,B34..45,NM") count the
+/// live cells within a larger range, and may also have dying states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// Bit n % 64 of table[n / 64] is set iff a cell with the 3x3 neighborhood
+    /// n (as described by [next_cell_state]) is alive in the next generation.
+    table: [u64; 8],
+
+    /// Number of states, including dead and alive. 2 for all but Generations
+    /// (and some Larger than Life) rules.
+    states: u8,
+
+    /// The neighborhood and counts of a Larger than Life rule, which replace
+    /// [table].
+    larger_than_life: Option<LargerThanLife>,
+}
+
+impl Rule {
+    /// Conway's Game of Life, B3/S23.
+    pub const CONWAY: Rule = Rule {
+        table: totalistic_table(1 << 3, (1 << 2) | (1 << 3), Neighborhood::Moore),
+        states: 2,
+        larger_than_life: None,
+    };
+
+    /// Parses [rulestring], accepting both the "B3/S23" form (case-insensitive,
+    /// with an optional '/') and the legacy "23/3" survival/birth form. Either
+    /// may be followed by the number of states of a Generations rule, as in
+    /// "B2/S/C3" or "/2/3", and neighbor counts in the "B3/S23" form may be
+    /// followed by Hensel notation letters, as in "B2-a/S12" (in which case
+    /// a 'C' section must follow a '/', or be an uppercase 'C' after the 'S'
+    /// section, as in "B2-a/S12C3"). Rulestrings without letters may end
+    /// with 'V' or 'H' to count the von Neumann or hexagonal neighborhood
+    /// instead of the Moore neighborhood. MAP rules are "MAP" followed by the
+    /// base64 of their table, and Larger than Life rules are written like
+    /// "R5,C0,M1,S34..58,B34..45,NM".
+    pub fn parse(rulestring: &str) -> Result<Rule, String> {
+        let trimmed = rulestring.trim();
+        let err = |reason: String| format!("Invalid rulestring {:?}: {}", rulestring, reason);
+
+        if trimmed.is_empty() {
+            return Err(err("rulestring is empty".into()));
+        }
+
+        if trimmed.starts_with(['R', 'r']) {
+            let (larger_than_life, states) = LargerThanLife::parse(trimmed).map_err(err)?;
+            return Ok(Rule { table: [0; 8], states, larger_than_life: Some(larger_than_life) });
+        }
+
+        if trimmed.get(..3).is_some_and(|prefix| prefix.eq_ignore_ascii_case("MAP")) {
+            let table = map::parse(&trimmed[3..]).map_err(err)?;
+            return Ok(Rule { table, states: 2, larger_than_life: None });
+        }
+
+        let (trimmed, neighbors) = match trimmed.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('V') => (&trimmed[..trimmed.len() - 1], Neighborhood::VonNeumann),
+            Some('H') => (&trimmed[..trimmed.len() - 1], Neighborhood::Hexagonal),
+            _ => (trimmed, Neighborhood::Moore),
+        };
+
+        let has_prefix = trimmed.chars().any(|c| c.is_ascii_alphabetic());
+
+        let (birth, survival, states) = if has_prefix {
+            let mut birth = None;
+            let mut survival = None;
+            let mut states = None;
+
+            // Each section is a 'B', 'S' or 'C' followed by its digits, and
+            // for 'B' and 'S', letters. 'c' is a letter unless it starts a section,
+            // as an uppercase 'C' followed by digits does after the 'S' section.
+            let mut section: Option<(char, String)> = None;
+            let mut chars = trimmed.chars().chain(std::iter::once('/')).peekable();
+
+            while let Some(c) = chars.next() {
+                let starts_states = c == 'C' && chars.peek().is_some_and(char::is_ascii_digit);
+
+                if starts_states && matches!(&section, Some(('b', digits)) if !digits.is_empty()) {
+                    return Err(err("'C' followed by digits in the 'B' section could be a letter or the number of states; \
+                        write letters in lowercase, or put the states after a '/'".into()));
+                }
+
+                let is_letter = matches!(&section, Some(('b' | 's', digits)) if !digits.is_empty()) && !starts_states;
+
+                match c.to_ascii_lowercase() {
+                    'c' if is_letter => section.as_mut().unwrap().1.push(c),
+                    'b' | 's' | 'c' | '/' => {
+                        if let Some((kind, digits)) = section.take() {
+                            let duplicate = match kind {
+                                'b' => birth.replace(Isotropic::parse_section(&digits).map_err(err)?).is_some(),
+                                's' => survival.replace(Isotropic::parse_section(&digits).map_err(err)?).is_some(),
+                                _ => states.replace(Self::parse_states(&digits).map_err(err)?).is_some(),
+                            };
+
+                            if duplicate {
+                                return Err(err(format!("'{}' appears more than once", kind.to_ascii_uppercase())));
+                            }
+                        }
+
+                        if c != '/' {
+                            section = Some((c.to_ascii_lowercase(), String::new()));
+                        }
+                    },
+                    digit => {
+                        let (_, digits) = section.as_mut()
+                            .ok_or_else(|| err(format!("'{}' is not preceded by 'B', 'S' or 'C'", digit)))?;
+                        digits.push(digit);
+                    },
+                }
+            }
+
+            (
+                birth.ok_or_else(|| err("missing 'B' section".into()))?,
+                survival.ok_or_else(|| err("missing 'S' section".into()))?,
+                states.unwrap_or(2),
+            )
+        } else {
+            let parts: Vec<&str> = trimmed.split('/').collect();
+            let (survival, birth, states) = match parts[..] {
+                [s, b] => (s, b, None),
+                [s, b, c] => (s, b, Some(c)),
+                _ => return Err(err("expected the form B<digits>/S<digits>[/C<states>] or <digits>/<digits>[/<states>]".into())),
+            };
+
+            (
+                Isotropic::parse_section(birth).map_err(err)?,
+                Isotropic::parse_section(survival).map_err(err)?,
+                states.map_or(Ok(2), Self::parse_states).map_err(err)?,
+            )
+        };
+
+        let table = match neighbors {
+            Neighborhood::Moore => Isotropic { birth, survival }.table(),
+            neighbors => {
+                let counts = |letters: &[u16; 9]| -> Result<u16, String> {
+                    let counts = Isotropic::counts(letters).ok_or_else(|| {
+                        format!("Hensel notation letters only apply to the Moore neighborhood, not the {} neighborhood", neighbors.name())
+                    })?;
+
+                    let most = neighbors.neighbors().count_ones();
+                    if counts >> (most + 1) != 0 {
+                        return Err(format!("a cell has at most {} neighbors in the {} neighborhood", most, neighbors.name()));
+                    }
+
+                    Ok(counts)
+                };
+
+                totalistic_table(counts(&birth).map_err(err)?, counts(&survival).map_err(err)?, neighbors)
+            },
+        };
+
+        Ok(Rule { table, states, larger_than_life: None })
+    }
+
+    /// Returns the number of states, including dead and alive.
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// Returns the neighborhood and counts of a Larger than Life rule, or
+    /// None for rules over the 8 nearest neighbors.
+    pub(crate) fn larger_than_life(&self) -> Option<&LargerThanLife> {
+        self.larger_than_life.as_ref()
+    }
+
+    /// Returns the name of the family of this, unless it is a two-state rule
+    /// over the 8 nearest neighbors, which all engines support.
+    pub(crate) fn family(&self) -> Option<&'static str> {
+        if self.larger_than_life.is_some() {
+            Some("Larger than Life")
+        } else if self.states > 2 {
+            Some("Generations")
+        } else {
+            None
+        }
+    }
+
+    /// Returns the neighborhood whose live cells this counts, or the Moore
+    /// neighborhood if this depends on how they are arranged.
+    pub fn neighborhood(&self) -> Neighborhood {
+        Neighborhood::ALL.iter().copied()
+            .find(|&neighbors| self.totalistic_masks(neighbors).is_some())
+            .unwrap_or(Neighborhood::Moore)
+    }
+
+    /// Returns the masks of counts of live [neighbors] for which a dead cell
+    /// comes alive and a live cell stays alive, unless this depends on more
+    /// than those counts.
+    pub(crate) fn totalistic_masks(&self, neighbors: Neighborhood) -> Option<(u16, u16)> {
+        let (mut birth, mut survival) = (0, 0);
+
+        for neighborhood in (0..512).filter(|&neighborhood| self.lives(neighborhood)) {
+            let count = (neighborhood & neighbors.neighbors()).count_ones();
+
+            if neighborhood & CENTER != 0 {
+                survival |= 1 << count;
+            } else {
+                birth |= 1 << count;
+            }
+        }
+
+        if totalistic_table(birth, survival, neighbors) == self.table {
+            Some((birth, survival))
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether rotating or reflecting a neighborhood never changes
+    /// its next state, as for all but some MAP rules.
+    pub(crate) fn is_isotropic(&self) -> bool {
+        self.larger_than_life.is_some() || Isotropic::from_table(&self.table).is_some()
+    }
+
+    /// Returns the state that a cell in [state] moves to in the next
+    /// generation under a rule over the 8 nearest neighbors. Bit
+    /// 3 * (dy + 1) + (dx + 1) of [neighborhood] is set iff the cell at offset
+    /// (dx, dy) is in state 1 (alive), including the cell itself (CENTER).
+    pub fn next_cell_state(&self, state: u8, neighborhood: u16) -> u8 {
+        self.step_state(state, self.lives(neighborhood))
+    }
+
+    /// Returns the state of a two-state cell in the next generation, where
+    /// [neighborhood] is as for [next_cell_state].
+    pub fn next_state(&self, neighborhood: u16) -> Cell {
+        if self.lives(neighborhood) {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+
+    /// Returns the state that a cell in [state] moves to, given whether it
+    /// would come alive (if dead) or stay alive (if alive).
+    pub(crate) fn step_state(&self, state: u8, lives: bool) -> u8 {
+        match state {
+            0 | 1 if lives => 1,
+            0 => 0,
+            // Dying states (including those beyond the last, from an earlier
+            // rule) count up to the last, then die.
+            state if state + 1 < self.states => state + 1,
+            _ => 0,
+        }
+    }
+
+    /// Returns whether the center of [neighborhood] is alive in the next generation.
+    fn lives(&self, neighborhood: u16) -> bool {
+        self.table[usize::from(neighborhood / 64)] & (1 << (neighborhood % 64)) != 0
+    }
+
+    /// Returns true iff a dead cell with no live neighbors comes alive, in which
+    /// case empty space doesn't stay empty.
+    pub fn has_b0(&self) -> bool {
+        match &self.larger_than_life {
+            Some(ltl) => ltl.is_born(0),
+            None => self.lives(0),
+        }
+    }
+
+    /// Parses [digits] as the number of states of a Generations rule.
+    fn parse_states(digits: &str) -> Result<u8, String> {
+        match digits.parse::<u32>() {
+            Ok(states) if (2..=u32::from(u8::MAX)).contains(&states) => Ok(states as u8),
+            Ok(states) => Err(format!("rules have between 2 and {} states, not {}", u8::MAX, states)),
+            Err(_) => Err(format!("{:?} is not a number of states", digits)),
+        }
+    }
+}
+
+/// Returns the table of the outer-totalistic rule with the [birth] and
+/// [survival] masks of counts of live [neighbors].
+const fn totalistic_table(birth: u16, survival: u16, neighbors: Neighborhood) -> [u64; 8] {
+    let mut table = [0; 8];
+    let mut neighborhood = 0;
+
+    while neighborhood < 512 {
+        let mask = if neighborhood & CENTER != 0 { survival } else { birth };
+
+        if mask & (1 << (neighborhood & neighbors.neighbors()).count_ones()) != 0 {
+            table[(neighborhood / 64) as usize] |= 1 << (neighborhood % 64);
+        }
+
+        neighborhood += 1;
+    }
+
+    table
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::CONWAY
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rule, String> {
+        Rule::parse(s)
+    }
+}
+
+impl fmt::Display for Rule {
+    /// Formats this as a canonical "B.../S..." rulestring (with Hensel notation
+    /// letters or a neighborhood suffix if needed), or else a MAP or Larger
+    /// than Life rulestring.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ltl) = &self.larger_than_life {
+            return ltl.fmt(self.states, f);
+        }
+
+        let write_states = |f: &mut fmt::Formatter<'_>| -> fmt::Result {
+            if self.states > 2 {
+                write!(f, "/C{}", self.states)?;
+            }
+
+            Ok(())
+        };
+
+        let neighbors = self.neighborhood();
+        if let Some((birth, survival)) = self.totalistic_masks(neighbors) {
+            let write_counts = |f: &mut fmt::Formatter<'_>, mask: u16| -> fmt::Result {
+                for n in 0..=8 {
+                    if mask & (1 << n) != 0 {
+                        write!(f, "{}", n)?;
+                    }
+                }
+
+                Ok(())
+            };
+
+            write!(f, "B")?;
+            write_counts(f, birth)?;
+            write!(f, "/S")?;
+            write_counts(f, survival)?;
+            write_states(f)?;
+
+            return write!(f, "{}", neighbors.suffix());
+        }
+
+        let isotropic = match Isotropic::from_table(&self.table) {
+            Some(isotropic) => isotropic,
+            None => return map::fmt(&self.table, f),
+        };
+
+        write!(f, "B")?;
+        Isotropic::fmt_section(&isotropic.birth, f)?;
+        write!(f, "/S")?;
+        Isotropic::fmt_section(&isotropic.survival, f)?;
+        write_states(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rule, CENTER};
+    use crate::Cell;
+
+    /// Returns the 3x3 neighborhood with [count] live neighbors, filled
+    /// clockwise from the north, around a cell that is alive iff [alive].
+    fn neighborhood(count: usize, alive: bool) -> u16 {
+        let clockwise = [1, 2, 5, 8, 7, 6, 3, 0];
+        let neighbors = clockwise[..count].iter().fold(0, |neighborhood, &bit| neighborhood | 1 << bit);
+
+        if alive { neighbors | CENTER } else { neighbors }
+    }
+
+    #[test]
+    fn parses_conway() {
+        for rulestring in &["B3/S23", "b3/s23", "B3S23", " B3/S23 ", "S23/B3", "23/3"] {
+            assert_eq!(Rule::parse(rulestring).unwrap(), Rule::CONWAY, "{}", rulestring);
+        }
+
+        assert_eq!("B3/S23".parse::<Rule>().unwrap(), Rule::CONWAY);
+        assert_eq!(Rule::default(), Rule::CONWAY);
+
+        for count in 0..=8 {
+            let born = if count == 3 { Cell::Alive } else { Cell::Dead };
+            let survives = if count == 2 || count == 3 { Cell::Alive } else { Cell::Dead };
+
+            assert_eq!(Rule::CONWAY.next_state(neighborhood(count, false)), born, "B{}", count);
+            assert_eq!(Rule::CONWAY.next_state(neighborhood(count, true)), survives, "S{}", count);
+        }
+    }
+
+    #[test]
+    fn formats_canonically() {
+        let rulestrings = [
+            ("B3/S23", "B3/S23"),
+            ("B36/S23", "B36/S23"),
+            ("b63/s32", "B36/S23"),
+            ("1357/1357", "B1357/S1357"),
+            ("B/S012345678", "B/S012345678"),
+            ("B0123478/S34678", "B0123478/S34678"),
+            ("/2/3", "B2/S/C3"),
+            ("B2/S/C3", "B2/S/C3"),
+        ];
+
+        for (rulestring, canonical) in &rulestrings {
+            let rule = Rule::parse(rulestring).unwrap();
+
+            assert_eq!(rule.to_string(), *canonical);
+            assert_eq!(Rule::parse(canonical).unwrap(), rule);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_rulestrings() {
+        let invalid = [
+            "", "   ", "B3", "S23", "B9/S23", "B3/S23/C1", "B3/S23/C256", "B3/S23/Cx", "3/2/1/0", "X3/S23",
+            "B3/S2/B3", "B3/S23/C3/C4", "3", "B3/S2z", "B3/S2-",
+        ];
+
+        for rulestring in &invalid {
+            let error = Rule::parse(rulestring).unwrap_err();
+            assert!(error.starts_with("Invalid rulestring"), "{}: {}", rulestring, error);
+        }
+    }
+
+    #[test]
+    fn detects_b0() {
+        assert!(Rule::parse("B0/S8").unwrap().has_b0());
+        assert!(!Rule::CONWAY.has_b0());
+    }
+
+    #[test]
+    fn parses_states_after_letters() {
+        let brian = Rule::parse("B2/S/C3").unwrap();
+
+        for rulestring in &["B3/S23/C3", "B3S23C3", "b3/s23/c3", "B3/S23C3"] {
+            let rule = Rule::parse(rulestring).unwrap();
+
+            assert_eq!(rule.states(), 3, "{}", rulestring);
+            assert_eq!(rule.to_string(), "B3/S23/C3");
+        }
+
+        assert_eq!(Rule::parse("B2S/C3").unwrap(), brian);
+        assert_eq!(Rule::parse("B2-a/S12C3").unwrap().to_string(), "B2-a/S12/C3");
+
+        // Lowercase 'c' after digits is still a letter.
+        assert_eq!(Rule::parse("B3/S2c3").unwrap().to_string(), "B3/S2c3");
+        assert_eq!(Rule::parse("B3/S2Ce").unwrap().to_string(), "B3/S2ce");
+        assert!(Rule::parse("B2C3/S23").unwrap_err().contains("could be a letter or the number of states"));
+    }
+}
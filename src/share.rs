@@ -0,0 +1,240 @@
+use std::convert::TryFrom;
+
+use wasm_bindgen::prelude::*;
+
+use crate::pattern::checked_cell_count;
+use crate::snapshot::{self, Reader};
+use crate::{Cell, Pattern, Rule, Universe};
+
+/// Version of the URL code format, its first byte once decoded.
+pub(crate) const VERSION: u8 = 1;
+
+/// How the cells of a URL code are stored.
+pub(crate) const CELLS_AS_RUNS: u8 = 0;
+pub(crate) const CELLS_AS_BITS: u8 = 1;
+
+/// The standard base64 alphabet of RFC 4648.
+pub(crate) const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The URL- and filename-safe base64 alphabet of RFC 4648.
+pub(crate) const BASE64_URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes [bytes] in unpadded base64 with [alphabet].
+pub(crate) fn encode_base64(bytes: &[u8], alphabet: &[u8; 64]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0_u32, |group, (idx, &byte)| group | u32::from(byte) << (16 - 8 * idx));
+
+        for sextet in 0..=chunk.len() {
+            encoded.push(alphabet[(group >> (18 - 6 * sextet) & 0x3f) as usize] as char);
+        }
+    }
+
+    encoded
+}
+
+/// Decodes unpadded base64 [text] written with [alphabet].
+pub(crate) fn decode_base64(text: &str, alphabet: &[u8; 64]) -> Result<Vec<u8>, String> {
+    // A final group of one character holds less than a byte.
+    if text.chars().count() % 4 == 1 {
+        return Err(format!("{} characters of base64 don't make whole bytes", text.chars().count()));
+    }
+
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut group, mut bits) = (0_u32, 0);
+
+    for c in text.chars() {
+        let sextet = alphabet.iter().position(|&digit| digit as char == c)
+            .ok_or_else(|| format!("unexpected character {:?}", c))?;
+
+        group = (group << 6) | sextet as u32;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+        }
+    }
+
+    Ok(bytes)
+}
+
+impl Pattern {
+    /// Encodes this as a compact string of the characters A-Z, a-z, 0-9, '-'
+    /// and '_', safe to use in URLs without escaping. The code includes the
+    /// dimensions and rule of this, but not its name or comments.
+    ///
+    /// Decoded from base64, the code is a version byte; the width and height
+    /// as varints; the rulestring as a little-endian u16 length followed by
+    /// the rulestring (empty if this has no rule); then the cells, row by row,
+    /// whichever way is shorter: a 0 followed by runs of equal cells, each a
+    /// varint length followed by the state, or a 1 followed by one bit per
+    /// cell, least significant bit first.
+    pub fn to_url_code(&self) -> String {
+        let mut bytes = vec![VERSION];
+
+        snapshot::write_varint(self.width().into(), &mut bytes);
+        snapshot::write_varint(self.height().into(), &mut bytes);
+        snapshot::write_string(&self.rule.map(|rule| rule.to_string()).unwrap_or_default(), &mut bytes);
+
+        let states: Vec<u8> = (0..self.height())
+            .flat_map(|y| (0..self.width()).map(move |x| (x, y)))
+            .map(|(x, y)| self.get_cell_at(x, y) as u8)
+            .collect();
+
+        let mut runs = Vec::new();
+        snapshot::write_cell_runs(&states, &mut runs);
+
+        let mut bits = vec![0_u8; states.len().div_ceil(8)];
+        for (idx, _) in states.iter().enumerate().filter(|&(_, &state)| state == Cell::Alive as u8) {
+            bits[idx / 8] |= 1 << (idx % 8);
+        }
+
+        if runs.len() <= bits.len() {
+            bytes.push(CELLS_AS_RUNS);
+            bytes.extend_from_slice(&runs);
+        } else {
+            bytes.push(CELLS_AS_BITS);
+            bytes.extend_from_slice(&bits);
+        }
+
+        encode_base64(&bytes, BASE64_URL)
+    }
+
+    /// Decodes a pattern encoded by [to_url_code]. Patterns more than 65536
+    /// cells across, or with more than 2^24 cells, are rejected.
+    pub fn from_url_code(code: &str) -> Result<Pattern, String> {
+        Pattern::read_url_code(code.trim()).map_err(|reason| format!("Invalid URL code: {}", reason))
+    }
+
+    fn read_url_code(code: &str) -> Result<Pattern, String> {
+        let bytes = decode_base64(code, BASE64_URL)?;
+        let mut reader = Reader::new(&bytes);
+
+        let version = reader.read_u8("version")?;
+        if version != VERSION {
+            return Err(format!("version {} isn't supported; only version {} is", version, VERSION));
+        }
+
+        let width = u32::try_from(reader.read_varint("width")?).map_err(|_| "the width is too large".to_string())?;
+        let height = u32::try_from(reader.read_varint("height")?).map_err(|_| "the height is too large".to_string())?;
+
+        let rulestring = reader.read_string("rulestring")?;
+        let rule = if rulestring.is_empty() { None } else { Some(Rule::parse(rulestring)?) };
+
+        // Checked before the cells are read, so that a short code can't claim
+        // enough cells to exhaust memory.
+        let cell_count = checked_cell_count(width, height)?;
+        let states = match reader.read_u8("cell format")? {
+            CELLS_AS_RUNS => reader.read_cell_runs(cell_count, 2)?,
+            CELLS_AS_BITS => {
+                let bits = reader.take(cell_count.div_ceil(8), "cells")?;
+
+                (0..cell_count)
+                    .map(|idx| (bits[idx / 8] >> (idx % 8)) & 1)
+                    .collect()
+            },
+            format => return Err(format!("unknown cell format {}", format)),
+        };
+
+        if !reader.is_done() {
+            return Err("unexpected data after the cells".into());
+        }
+
+        let mut pattern = Pattern::new(width, height);
+        pattern.rule = rule;
+
+        for (idx, &state) in states.iter().enumerate() {
+            let idx = idx as u32;
+            pattern.set_cell_at(idx % width, idx / width, Cell::from_state(state));
+        }
+
+        Ok(pattern)
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Loads the pattern in the URL code [code] into this with its top-left
+    /// corner at ([x], [y]).
+    pub fn load_url_code(&mut self, code: &str, x: u32, y: u32) -> Result<(), String> {
+        self.paste(&Pattern::from_url_code(code)?, x, y);
+        Ok(())
+    }
+
+    /// Creates a universe with the dimensions, rule and cells in the URL
+    /// code [code].
+    pub fn from_url_code(code: &str) -> Result<Universe, String> {
+        Ok(Universe::from_pattern(&Pattern::from_url_code(code)?))
+    }
+
+    /// Encodes the dimensions, rule and cells of this as a compact string
+    /// that is safe to use in URLs, such as in location.hash.
+    pub fn to_url_code(&self) -> String {
+        self.to_pattern().to_url_code()
+    }
+
+    /// Encodes the [width] x [height] region of this with its top-left corner
+    /// at ([x], [y]) as a URL code, as in [to_url_code]. The region must be
+    /// within the edges of this.
+    pub fn region_to_url_code(&self, x: u32, y: u32, width: u32, height: u32) -> Result<String, String> {
+        Ok(self.region_to_pattern(x, y, width, height)?.to_url_code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_base64, encode_base64, BASE64, BASE64_URL, CELLS_AS_BITS, CELLS_AS_RUNS};
+    use crate::testing::{cell_runs, forge_url_code};
+    use crate::{Pattern, Universe};
+
+    #[test]
+    fn base64_round_trips() {
+        for len in 0..10 {
+            let bytes: Vec<u8> = (0..len).map(|idx| (idx * 37 + 200) as u8).collect();
+
+            assert_eq!(decode_base64(&encode_base64(&bytes, BASE64), BASE64).unwrap(), bytes);
+            assert_eq!(decode_base64(&encode_base64(&bytes, BASE64_URL), BASE64_URL).unwrap(), bytes);
+        }
+
+        assert_eq!(encode_base64(b"Man", BASE64), "TWFu");
+        assert!(decode_base64("TW=u", BASE64).is_err());
+        assert!(decode_base64("T", BASE64).is_err());
+        assert!(decode_base64("TWFuT", BASE64).is_err());
+
+        let mut code = Universe::empty(3, 3).to_url_code();
+        while code.len() % 4 != 1 {
+            code.push('A');
+        }
+        assert!(Pattern::from_url_code(&code).unwrap_err().contains("whole bytes"));
+    }
+
+    #[test]
+    fn round_trips() {
+        let sparse = Pattern::from_rle("x = 40, y = 30, rule = B36/S23\nbo$2bo$3o27$39bo!").unwrap();
+        let mut dense = Universe::empty(20, 20);
+        dense.randomize(0.5, 9).unwrap();
+
+        for pattern in &[sparse, dense.to_pattern()] {
+            let code = pattern.to_url_code();
+
+            assert!(code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+            assert_eq!(Pattern::from_url_code(&code).unwrap(), *pattern);
+        }
+    }
+
+    #[test]
+    fn rejects_hostile_codes() {
+        let huge_run = cell_runs(&[(65535 * 65535, 0)]);
+
+        assert!(Universe::from_url_code(&forge_url_code(65535, 65535, CELLS_AS_RUNS, &huge_run)).is_err());
+        assert!(Universe::from_url_code(&forge_url_code(65535, 65535, CELLS_AS_BITS, &[0; 16])).is_err());
+        assert!(Universe::from_url_code(&forge_url_code(1 << 40, 1, CELLS_AS_RUNS, &huge_run)).is_err());
+        assert!(Universe::from_url_code(&forge_url_code(3, 3, CELLS_AS_RUNS, &huge_run)).is_err());
+        assert!(Universe::from_url_code(&forge_url_code(3, 3, CELLS_AS_BITS, &[0xff])).is_err());
+        assert!(Universe::from_url_code(&forge_url_code(3, 3, 7, &[])).is_err());
+        assert!(Universe::from_url_code("not a code!").is_err());
+        assert!(Universe::from_url_code("").is_err());
+    }
+}
@@ -0,0 +1,330 @@
+use std::convert::TryFrom;
+
+use wasm_bindgen::prelude::*;
+
+use crate::pattern::checked_cell_count;
+use crate::{Backend, Rule, Topology, Universe};
+
+/// First bytes of every snapshot.
+pub(crate) const MAGIC: &[u8; 4] = b"GOLS";
+
+/// Version of the snapshot format written by [Universe::to_snapshot]. Snapshots
+/// with a later version are rejected rather than misread.
+pub(crate) const VERSION: u16 = 1;
+
+/// Returns the CRC-32 (as used by zip and PNG) of [bytes].
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+/// Appends [value] as an unsigned LEB128 varint: 7 bits per byte, least
+/// significant first, with the high bit set on all but the last byte.
+pub(crate) fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+
+    out.push(value as u8);
+}
+
+/// Appends [string] as a u16 length followed by its UTF-8 bytes.
+pub(crate) fn write_string(string: &str, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(string.len() as u16).to_le_bytes());
+    out.extend_from_slice(string.as_bytes());
+}
+
+/// Appends the row-major cell [states] as runs of equal states: each a varint
+/// length followed by the state as a u8.
+pub(crate) fn write_cell_runs(states: &[u8], out: &mut Vec<u8>) {
+    let mut start = 0;
+
+    while start < states.len() {
+        let state = states[start];
+        let len = states[start..].iter().take_while(|&&other| other == state).count();
+
+        write_varint(len as u64, out);
+        out.push(state);
+        start += len;
+    }
+}
+
+/// Reads the fields of a snapshot (or another format built from the same
+/// parts) in order.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, offset: 0 }
+    }
+
+    /// Returns whether all bytes have been read.
+    pub fn is_done(&self) -> bool {
+        self.offset == self.bytes.len()
+    }
+
+    pub fn take(&mut self, len: usize, what: &str) -> Result<&'a [u8], String> {
+        let end = self.offset.checked_add(len).filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| format!("data ends in the middle of the {}", what))?;
+
+        let taken = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(taken)
+    }
+
+    pub fn read_u8(&mut self, what: &str) -> Result<u8, String> {
+        Ok(self.take(1, what)?[0])
+    }
+
+    fn read_u16(&mut self, what: &str) -> Result<u16, String> {
+        let bytes = self.take(2, what)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&mut self, what: &str) -> Result<u32, String> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4, what)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_u64(&mut self, what: &str) -> Result<u64, String> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8, what)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    pub fn read_varint(&mut self, what: &str) -> Result<u64, String> {
+        let mut value = 0_u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8(what)?;
+            value |= u64::from(byte & 0x7f) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(format!("the {} is too large", what))
+    }
+
+    /// Reads [cell_count] cell states written by [write_cell_runs], each of
+    /// which must be less than [states]. [cell_count] should come from
+    /// [checked_cell_count], as cells are only allocated as runs are read.
+    pub fn read_cell_runs(&mut self, cell_count: usize, states: u8) -> Result<Vec<u8>, String> {
+        let mut cells = Vec::new();
+
+        while cells.len() < cell_count {
+            let len = self.read_varint("cell run length")?;
+            let len = usize::try_from(len).ok().filter(|&len| len > 0 && len <= cell_count - cells.len())
+                .ok_or_else(|| format!("a run of {} cells doesn't fit in the {} cells left", len, cell_count - cells.len()))?;
+
+            let state = self.read_u8("cell state")?;
+            if state >= states {
+                return Err(format!("cell state {} is beyond the last of the rule's {} states", state, states));
+            }
+
+            cells.resize(cells.len() + len, state);
+        }
+
+        Ok(cells)
+    }
+
+    /// Reads a u16 length followed by that many bytes of UTF-8.
+    pub fn read_string(&mut self, what: &str) -> Result<&'a str, String> {
+        let len = self.read_u16(&format!("{} length", what))?;
+
+        std::str::from_utf8(self.take(len.into(), what)?)
+            .map_err(|_| format!("{} isn't valid UTF-8", what))
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Saves the cells, rule, topology, backend, generation, dimensions and
+    /// display settings of this, to be restored by [from_snapshot]. Undo
+    /// history, generation history, statistics and cycle detection aren't saved.
+    ///
+    /// A snapshot is, with integers little-endian:
+    ///   "GOLS", then the format version as a u16;
+    ///   the width and height as u32s and the generation as a u64;
+    ///   the square size and spacing as u32s;
+    ///   the topology and backend as u8s;
+    ///   the length of the rulestring as a u16, followed by the rulestring;
+    ///   the cells, row by row, as runs of equal cells: each a varint length
+    ///   followed by the state as a u8;
+    ///   a CRC-32 of everything before it, as a u32.
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.extend_from_slice(&self.generation.to_le_bytes());
+        out.extend_from_slice(&self.square_size_px.to_le_bytes());
+        out.extend_from_slice(&self.square_spacing_px.to_le_bytes());
+        out.push(self.topology as u8);
+        out.push(self.backend() as u8);
+
+        write_string(&self.rule.to_string(), &mut out);
+        write_cell_runs(&self.cells.to_vec(), &mut out);
+
+        let checksum = crc32(&out);
+        out.extend_from_slice(&checksum.to_le_bytes());
+
+        out
+    }
+
+    /// Restores a universe saved by [to_snapshot]. Universes more than 65536
+    /// cells across, or with more than 2^24 cells, are rejected.
+    pub fn from_snapshot(snapshot: &[u8]) -> Result<Universe, String> {
+        Universe::read_snapshot(snapshot).map_err(|reason| format!("Invalid snapshot: {}", reason))
+    }
+}
+
+// Private impl
+impl Universe {
+    fn read_snapshot(snapshot: &[u8]) -> Result<Universe, String> {
+        let mut reader = Reader::new(snapshot);
+
+        if reader.take(MAGIC.len(), "magic") != Ok(&MAGIC[..]) {
+            return Err(format!("data doesn't begin with {:?}", String::from_utf8_lossy(MAGIC)));
+        }
+
+        let version = reader.read_u16("version")?;
+        if version > VERSION {
+            return Err(format!("version {} is newer than the latest supported version, {}", version, VERSION));
+        }
+
+        // Check the checksum before reading further, so that corrupt data is
+        // reported as such rather than by whichever field it garbled.
+        let body_len = snapshot.len().checked_sub(4).filter(|&len| len >= reader.offset)
+            .ok_or_else(|| "data ends before the checksum".to_string())?;
+        let mut checksum = [0; 4];
+        checksum.copy_from_slice(&snapshot[body_len..]);
+
+        if crc32(&snapshot[..body_len]) != u32::from_le_bytes(checksum) {
+            return Err("checksum doesn't match; the data is corrupt".into());
+        }
+
+        reader.bytes = &snapshot[..body_len];
+
+        let width = reader.read_u32("width")?;
+        let height = reader.read_u32("height")?;
+        let generation = reader.read_u64("generation")?;
+        let square_size = reader.read_u32("square size")?;
+        let square_spacing = reader.read_u32("square spacing")?;
+
+        let topology = match reader.read_u8("topology")? {
+            0 => Topology::Torus,
+            1 => Topology::Plane,
+            2 => Topology::Cylinder,
+            3 => Topology::KleinBottle,
+            4 => Topology::CrossSurface,
+            topology => return Err(format!("unknown topology {}", topology)),
+        };
+
+        let backend = match reader.read_u8("backend")? {
+            0 => Backend::Bytes,
+            1 => Backend::BitPacked,
+            backend => return Err(format!("unknown backend {}", backend)),
+        };
+
+        let rule = Rule::parse(reader.read_string("rulestring")?)?;
+
+        let cell_count = checked_cell_count(width, height)?;
+        let cells = reader.read_cell_runs(cell_count, rule.states())?;
+
+        if !reader.is_done() {
+            return Err(format!("{} unexpected bytes after the cells", reader.bytes.len() - reader.offset));
+        }
+
+        let mut universe = Universe::with_states(width, height, cells);
+        universe.use_rule(rule);
+        universe.set_topology(topology);
+        universe.set_backend(backend);
+        universe.generation = generation;
+        universe.square_size_px = square_size;
+        universe.square_spacing_px = square_spacing;
+
+        Ok(universe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::forge_snapshot;
+    use crate::{Topology, Universe};
+
+    #[test]
+    fn round_trips() {
+        let mut universe = Universe::empty(30, 20);
+        universe.set_rule("B36/S23").unwrap();
+        universe.set_topology(Topology::KleinBottle);
+        universe.randomize(0.3, 5).unwrap();
+        universe.tick();
+
+        let restored = Universe::from_snapshot(&universe.to_snapshot()).unwrap();
+
+        assert_eq!(restored.to_snapshot(), universe.to_snapshot());
+        assert_eq!(restored.generation(), 1);
+        assert_eq!(restored.rule(), "B36/S23");
+        assert_eq!(restored.topology(), Topology::KleinBottle);
+        assert_eq!(restored.cells.to_vec(), universe.cells.to_vec());
+    }
+
+    #[test]
+    fn accepts_forged_snapshot() {
+        let universe = Universe::from_snapshot(&forge_snapshot(3, 2, &[(2, 0), (2, 1), (1, 0), (1, 1)])).unwrap();
+        assert_eq!(universe.cells.to_vec(), vec![0, 0, 1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn rejects_corrupt() {
+        let snapshot = Universe::empty(10, 10).to_snapshot();
+
+        for idx in 0..snapshot.len() {
+            let mut corrupt = snapshot.clone();
+            corrupt[idx] ^= 0x10;
+            assert!(Universe::from_snapshot(&corrupt).is_err(), "byte {}", idx);
+        }
+
+        assert!(Universe::from_snapshot(b"GOLF").is_err());
+        assert!(Universe::from_snapshot(&forge_snapshot(3, 2, &[(6, 2)])).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated() {
+        let snapshot = Universe::new(10, 10).to_snapshot();
+
+        for len in 0..snapshot.len() {
+            assert!(Universe::from_snapshot(&snapshot[..len]).is_err(), "length {}", len);
+        }
+
+        assert!(Universe::from_snapshot(&forge_snapshot(3, 2, &[(5, 0)])).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized() {
+        assert!(Universe::from_snapshot(&forge_snapshot(u32::MAX, u32::MAX, &[(1, 0)])).is_err());
+        assert!(Universe::from_snapshot(&forge_snapshot(65535, 65535, &[(65535 * 65535, 0)])).is_err());
+        assert!(Universe::from_snapshot(&forge_snapshot(100_000, 1, &[(100_000, 0)])).is_err());
+        assert!(Universe::from_snapshot(&forge_snapshot(3, 2, &[(7, 0)])).is_err());
+        assert!(Universe::from_snapshot(&forge_snapshot(3, 2, &[(u64::MAX, 0)])).is_err());
+        assert!(Universe::from_snapshot(&forge_snapshot(3, 2, &[(4, 0), (3, 1)])).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Universe};
+
+/// The SplitMix64 pseudorandom number generator. It's simple to reimplement
+/// in any language, so soups can be reproduced from their seed elsewhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in [0, 1) made from the top 53 bits of [next_u64].
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Replaces all cells with a random soup where each cell is alive with
+    /// probability [density]. The same [seed] always gives the same soup.
+    pub fn randomize(&mut self, density: f64, seed: u64) -> Result<(), String> {
+        self.randomize_region(0, 0, self.width, self.height, density, seed)
+    }
+
+    /// Replaces the cells of the [width] x [height] region with its top-left
+    /// corner at ([x], [y]) with a random soup, as in [randomize]. Cells
+    /// outside of the region are unchanged, and cells beyond the edges of this
+    /// are mapped according to its topology.
+    ///
+    /// Cells are filled row by row, each with the next output of a SplitMix64
+    /// generator seeded with [seed]: a cell is alive if the output's top 53
+    /// bits, as a fraction of 2^53, are less than [density].
+    pub fn randomize_region(&mut self, x: u32, y: u32, width: u32, height: u32, density: f64, seed: u64) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&density) {
+            return Err(format!("Soup density must be between 0 and 1, not {}", density));
+        }
+
+        let mut rng = SplitMix64::new(seed);
+
+        self.journal.begin();
+
+        for dy in 0..height {
+            for dx in 0..width {
+                let cell = if rng.next_f64() < density { Cell::Alive } else { Cell::Dead };
+
+                if let Some((x, y)) = self.resolve(i64::from(x) + i64::from(dx), i64::from(y) + i64::from(dy)) {
+                    self.edit_cell(x, y, cell);
+                }
+            }
+        }
+
+        self.journal.end();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SplitMix64;
+    use crate::testing::{live_cells, states};
+    use crate::{Topology, Universe};
+
+    #[test]
+    fn matches_reference_outputs() {
+        let mut rng = SplitMix64::new(0);
+
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        assert_eq!(rng.next_u64(), 0x06c4_5d18_8009_454f);
+    }
+
+    #[test]
+    fn reproduces_soups_from_seeds() {
+        let soup = |seed| {
+            let mut universe = Universe::empty(32, 32);
+            universe.randomize(0.5, seed).unwrap();
+            states(&universe)
+        };
+
+        assert_eq!(soup(1), soup(1));
+        assert_ne!(soup(1), soup(2));
+    }
+
+    #[test]
+    fn fills_by_density() {
+        let mut universe = Universe::empty(16, 16);
+
+        universe.randomize(1.0, 7).unwrap();
+        assert_eq!(universe.population(), 16 * 16);
+
+        universe.randomize(0.0, 7).unwrap();
+        assert_eq!(universe.population(), 0);
+
+        assert!(universe.randomize(-0.1, 7).is_err());
+        assert!(universe.randomize(1.5, 7).is_err());
+        assert!(universe.randomize(f64::NAN, 7).is_err());
+    }
+
+    #[test]
+    fn fills_only_the_region() {
+        let mut universe = Universe::empty(8, 8);
+        universe.randomize_region(6, 2, 3, 2, 1.0, 7).unwrap();
+        assert_eq!(live_cells(&universe), vec![(0, 2), (6, 2), (7, 2), (0, 3), (6, 3), (7, 3)]);
+
+        let mut universe = Universe::empty(8, 8);
+        universe.set_topology(Topology::Plane);
+        universe.randomize_region(6, 2, 3, 2, 1.0, 7).unwrap();
+        assert_eq!(live_cells(&universe), vec![(6, 2), (7, 2), (6, 3), (7, 3)]);
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cell, Universe};
+
+/// Default number of generations remembered by the population history.
+pub const DEFAULT_POPULATION_HISTORY_LENGTH: usize = 1024;
+
+/// Births and deaths counted while computing a generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct TickCounts {
+    pub births: u32,
+    pub deaths: u32,
+}
+
+impl TickCounts {
+    /// Counts a cell going from [before] to [after].
+    pub fn record(&mut self, before: Cell, after: Cell) {
+        match (before, after) {
+            (Cell::Dead, Cell::Alive) => self.births += 1,
+            (Cell::Alive, Cell::Dead) => self.deaths += 1,
+            _ => {},
+        }
+    }
+
+    /// Reverses [record]ing a cell going from [before] to [after].
+    pub fn unrecord(&mut self, before: Cell, after: Cell) {
+        match (before, after) {
+            (Cell::Dead, Cell::Alive) => self.births -= 1,
+            (Cell::Alive, Cell::Dead) => self.deaths -= 1,
+            _ => {},
+        }
+    }
+}
+
+/// Population statistics of a universe, kept up to date as it's ticked
+/// and edited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Stats {
+    population: u32,
+
+    /// Births and deaths of the last tick.
+    last_tick: TickCounts,
+
+    /// Population after each of the most recent ticks, oldest first.
+    population_history: VecDeque<u32>,
+    population_history_length: usize,
+}
+
+impl Stats {
+    pub fn new(population: u32) -> Stats {
+        Stats {
+            population,
+            last_tick: TickCounts::default(),
+
+            population_history: VecDeque::new(),
+            population_history_length: DEFAULT_POPULATION_HISTORY_LENGTH,
+        }
+    }
+
+    pub fn population(&self) -> u32 {
+        self.population
+    }
+
+    pub fn last_tick(&self) -> TickCounts {
+        self.last_tick
+    }
+
+    /// Accounts for a cell edited from [before] to [after].
+    pub fn record_edit(&mut self, before: Cell, after: Cell) {
+        match (before, after) {
+            (Cell::Dead, Cell::Alive) => self.population += 1,
+            (Cell::Alive, Cell::Dead) => self.population -= 1,
+            _ => {},
+        }
+    }
+
+    /// Replaces the population after cells were changed wholesale.
+    pub fn reset_population(&mut self, population: u32) {
+        self.population = population;
+    }
+
+    /// Accounts for a tick with [counts] births and deaths.
+    pub fn record_tick(&mut self, counts: TickCounts) {
+        self.population = self.population + counts.births - counts.deaths;
+        self.last_tick = counts;
+
+        self.population_history.push_back(self.population);
+        self.enforce_history_length();
+    }
+
+    /// Accounts for stepping back a generation. The births and deaths of the
+    /// generation before aren't known, so they're reported as zero.
+    pub fn record_step_back(&mut self) {
+        self.last_tick = TickCounts::default();
+        self.population_history.pop_back();
+    }
+
+    /// Accounts for jumping ahead any number of generations, to the current
+    /// population. As for [record_step_back], births and deaths are reported
+    /// as zero.
+    pub fn record_jump(&mut self) {
+        self.last_tick = TickCounts::default();
+
+        self.population_history.push_back(self.population);
+        self.enforce_history_length();
+    }
+
+    pub fn population_history(&self) -> Vec<u32> {
+        self.population_history.iter().copied().collect()
+    }
+
+    pub fn population_history_length(&self) -> usize {
+        self.population_history_length
+    }
+
+    pub fn set_population_history_length(&mut self, length: usize) {
+        self.population_history_length = length;
+        self.enforce_history_length();
+    }
+
+    fn enforce_history_length(&mut self) {
+        while self.population_history.len() > self.population_history_length {
+            self.population_history.pop_front();
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// Returns the number of live cells.
+    pub fn population(&self) -> u32 {
+        self.stats.population()
+    }
+
+    /// Returns the number of cells that came alive in the last tick.
+    pub fn births(&self) -> u32 {
+        self.stats.last_tick().births
+    }
+
+    /// Returns the number of cells that died in the last tick.
+    pub fn deaths(&self) -> u32 {
+        self.stats.last_tick().deaths
+    }
+
+    /// Returns the population after each of the most recent ticks, oldest
+    /// first, for plotting. Edits made between ticks are included in the
+    /// population of the following tick.
+    pub fn population_history(&self) -> Vec<u32> {
+        self.stats.population_history()
+    }
+
+    /// Sets the number of ticks remembered by [population_history].
+    pub fn set_population_history_length(&mut self, length: usize) {
+        self.stats.set_population_history_length(length);
+    }
+
+    pub fn population_history_length(&self) -> usize {
+        self.stats.population_history_length()
+    }
+}
+
+// Private impl
+impl Universe {
+    /// Counts the live cells by scanning all of them. Used only when cells are
+    /// replaced wholesale; otherwise the population is kept up to date by
+    /// [tick] and edits.
+    pub(crate) fn count_population(&self) -> u32 {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.cells.get(x, y, self.width) == Cell::Alive)
+            .count() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Universe;
+
+    /// Returns an 8x8 universe with a vertical blinker in the middle.
+    fn blinker() -> Universe {
+        let mut universe = Universe::empty(8, 8);
+
+        for y in 3..6 {
+            universe.toggle_cell_at(4, y);
+        }
+
+        universe
+    }
+
+    #[test]
+    fn counts_ticks_and_edits() {
+        let mut universe = blinker();
+        assert_eq!((universe.population(), universe.births(), universe.deaths()), (3, 0, 0));
+        assert_eq!(universe.population_history(), vec![]);
+
+        universe.tick();
+        assert_eq!((universe.population(), universe.births(), universe.deaths()), (3, 2, 2));
+        assert_eq!(universe.population_history(), vec![3]);
+
+        // Edits change the population, but not the counts of the last tick.
+        universe.toggle_cell_at(0, 0);
+        assert_eq!((universe.population(), universe.births(), universe.deaths()), (4, 2, 2));
+        assert_eq!(universe.population_history(), vec![3]);
+
+        universe.tick();
+        assert_eq!((universe.population(), universe.births(), universe.deaths()), (3, 2, 3));
+        assert_eq!(universe.population_history(), vec![3, 3]);
+    }
+
+    #[test]
+    fn limits_population_history() {
+        let mut universe = blinker();
+        universe.toggle_cell_at(0, 0);
+
+        for _ in 0..3 {
+            universe.tick();
+        }
+        assert_eq!(universe.population_history(), vec![3, 3, 3]);
+
+        universe.set_population_history_length(2);
+        assert_eq!(universe.population_history_length(), 2);
+        assert_eq!(universe.population_history(), vec![3, 3]);
+
+        universe.toggle_cell_at(0, 0);
+        universe.tick();
+        assert_eq!(universe.population_history(), vec![3, 3]);
+
+        universe.set_population_history_length(0);
+        universe.tick();
+        assert_eq!(universe.population_history(), vec![]);
+    }
+
+    #[test]
+    fn steps_back() {
+        let mut universe = blinker();
+        universe.set_history_depth(4);
+        universe.toggle_cell_at(0, 0);
+
+        universe.tick();
+        universe.tick();
+        assert_eq!(universe.population_history(), vec![3, 3]);
+
+        assert_eq!(universe.step_back(2), 2);
+        assert_eq!((universe.population(), universe.births(), universe.deaths()), (4, 0, 0));
+        assert_eq!(universe.population_history(), vec![]);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::share::{encode_base64, BASE64_URL};
+use crate::snapshot::{crc32, write_string, write_varint, MAGIC};
+use crate::{share, snapshot, Backend, Cell, Topology, Universe};
+
+/// Returns the row-major states of all cells of [universe].
+pub fn states(universe: &Universe) -> Vec<u8> {
+    (0..universe.height())
+        .flat_map(|y| (0..universe.width()).map(move |x| (x, y)))
+        .map(|(x, y)| universe.get_state_at(x, y))
+        .collect()
+}
+
+/// Returns the coordinates of the live cells of [universe], in row-major order.
+pub fn live_cells(universe: &Universe) -> Vec<(u32, u32)> {
+    (0..universe.height())
+        .flat_map(|y| (0..universe.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| universe.get_cell_at(x, y) == Cell::Alive)
+        .collect()
+}
+
+/// Encodes [runs] of (length, state) as written by [snapshot::write_cell_runs],
+/// without checking that they add up to anything.
+pub fn cell_runs(runs: &[(u64, u8)]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    for &(len, state) in runs {
+        write_varint(len, &mut out);
+        out.push(state);
+    }
+
+    out
+}
+
+/// Builds a snapshot of a Conway's Life universe with the given header
+/// dimensions and cell [runs], with a valid checksum.
+pub fn forge_snapshot(width: u32, height: u32, runs: &[(u64, u8)]) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&snapshot::VERSION.to_le_bytes());
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    out.extend_from_slice(&0_u64.to_le_bytes());
+    out.extend_from_slice(&8_u32.to_le_bytes());
+    out.extend_from_slice(&1_u32.to_le_bytes());
+    out.extend_from_slice(&[Topology::Torus as u8, Backend::Bytes as u8]);
+    write_string("B3/S23", &mut out);
+    out.extend_from_slice(&cell_runs(runs));
+
+    let checksum = crc32(&out);
+    out.extend_from_slice(&checksum.to_le_bytes());
+
+    out
+}
+
+/// Builds a URL code with the given header dimensions, cell [format] and
+/// [cells] bytes.
+pub fn forge_url_code(width: u64, height: u64, format: u8, cells: &[u8]) -> String {
+    let mut bytes = vec![share::VERSION];
+    write_varint(width, &mut bytes);
+    write_varint(height, &mut bytes);
+    write_string("", &mut bytes);
+    bytes.push(format);
+    bytes.extend_from_slice(cells);
+
+    encode_base64(&bytes, BASE64_URL)
+}
@@ -0,0 +1,166 @@
+/// Side length of a tile, in cells. Matches the width of a [BitGrid] word, so
+/// that each row of a tile is a single word.
+pub const TILE_SIZE: u32 = 64;
+
+/// Tracks which square tiles of a universe changed in the last generation
+/// (or were edited since), so that [Universe::tick] can skip the rest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Tiles {
+    width: u32,
+    height: u32,
+
+    columns: u32,
+    rows: u32,
+    changed: Vec<bool>,
+}
+
+impl Tiles {
+    /// Creates tiles covering a [width] x [height] universe, all marked as changed.
+    pub fn new(width: u32, height: u32) -> Tiles {
+        let columns = width.div_ceil(TILE_SIZE);
+        let rows = height.div_ceil(TILE_SIZE);
+
+        Tiles {
+            width,
+            height,
+
+            columns,
+            rows,
+            changed: vec![true; (columns * rows) as usize],
+        }
+    }
+
+    /// Returns the width of the universe these tiles cover.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the coordinates of all cells in the tiles flagged in [flagged],
+    /// one tile at a time.
+    pub fn cells_in<'a>(&'a self, flagged: &'a [bool]) -> impl Iterator<Item = (u32, u32)> + 'a {
+        (0..self.rows * self.columns)
+            .filter(move |&tile| flagged[tile as usize])
+            .flat_map(move |tile| {
+                let (left, top) = ((tile % self.columns) * TILE_SIZE, (tile / self.columns) * TILE_SIZE);
+                let (right, bottom) = ((left + TILE_SIZE).min(self.width), (top + TILE_SIZE).min(self.height));
+
+                (top..bottom).flat_map(move |y| (left..right).map(move |x| (x, y)))
+            })
+    }
+
+    /// Returns the index of the tile containing the cell at ([x], [y]).
+    pub fn tile_of(&self, x: u32, y: u32) -> usize {
+        ((y / TILE_SIZE) * self.columns + x / TILE_SIZE) as usize
+    }
+
+    /// Marks the tile containing the cell at ([x], [y]) as changed.
+    pub fn mark_cell(&mut self, x: u32, y: u32) {
+        let tile = self.tile_of(x, y);
+        self.changed[tile] = true;
+    }
+
+    pub fn mark_all(&mut self) {
+        self.changed.iter_mut().for_each(|changed| *changed = true);
+    }
+
+    /// Replaces the set of changed tiles with [changed].
+    pub fn set_changed(&mut self, changed: Vec<bool>) {
+        self.changed = changed;
+    }
+
+    /// Returns the indices of all changed tiles.
+    pub fn changed_indices(&self) -> Vec<u32> {
+        (0..self.changed.len() as u32).filter(|&idx| self.changed[idx as usize]).collect()
+    }
+
+    /// Returns which tiles need to be recomputed by the next tick: the changed
+    /// tiles and their neighbors. If [edges_wrap], a changed tile on the
+    /// edge of the universe also affects all other tiles on the edge.
+    pub fn to_recompute(&self, edges_wrap: bool) -> Vec<bool> {
+        let mut recompute = vec![false; self.changed.len()];
+        let mut edge_changed = false;
+
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                if !self.changed[(row * self.columns + column) as usize] {
+                    continue;
+                }
+
+                edge_changed |= self.is_edge(column, row);
+
+                for neighbor_row in row.saturating_sub(1)..(row + 2).min(self.rows) {
+                    for neighbor_column in column.saturating_sub(1)..(column + 2).min(self.columns) {
+                        recompute[(neighbor_row * self.columns + neighbor_column) as usize] = true;
+                    }
+                }
+            }
+        }
+
+        if edges_wrap && edge_changed {
+            for row in 0..self.rows {
+                for column in 0..self.columns {
+                    if self.is_edge(column, row) {
+                        recompute[(row * self.columns + column) as usize] = true;
+                    }
+                }
+            }
+        }
+
+        recompute
+    }
+
+    fn is_edge(&self, column: u32, row: u32) -> bool {
+        column == 0 || row == 0 || column + 1 == self.columns || row + 1 == self.rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tiles, TILE_SIZE};
+
+    /// Returns 4x4 tiles with only the one at ([column], [row]) changed.
+    fn changed_at(column: u32, row: u32) -> Tiles {
+        let mut tiles = Tiles::new(4 * TILE_SIZE, 4 * TILE_SIZE);
+        tiles.set_changed(vec![false; 16]);
+        tiles.mark_cell(column * TILE_SIZE, row * TILE_SIZE);
+        tiles
+    }
+
+    /// Returns the indices of the tiles flagged in [flagged].
+    fn indices(flagged: &[bool]) -> Vec<usize> {
+        (0..flagged.len()).filter(|&idx| flagged[idx]).collect()
+    }
+
+    #[test]
+    fn finds_tiles_of_cells() {
+        let tiles = Tiles::new(3 * TILE_SIZE - 10, 2 * TILE_SIZE);
+
+        assert_eq!(tiles.tile_of(0, 0), 0);
+        assert_eq!(tiles.tile_of(TILE_SIZE - 1, TILE_SIZE - 1), 0);
+        assert_eq!(tiles.tile_of(TILE_SIZE, 0), 1);
+        assert_eq!(tiles.tile_of(2 * TILE_SIZE, TILE_SIZE), 5);
+        assert_eq!(tiles.changed_indices(), vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn recomputes_neighbors_of_changed_tiles() {
+        let interior = changed_at(1, 1);
+        assert_eq!(interior.changed_indices(), vec![5]);
+        assert_eq!(indices(&interior.to_recompute(false)), vec![0, 1, 2, 4, 5, 6, 8, 9, 10]);
+        assert_eq!(indices(&interior.to_recompute(true)), vec![0, 1, 2, 4, 5, 6, 8, 9, 10]);
+
+        let corner = changed_at(0, 0);
+        assert_eq!(indices(&corner.to_recompute(false)), vec![0, 1, 4, 5]);
+        assert_eq!(indices(&corner.to_recompute(true)), vec![0, 1, 2, 3, 4, 5, 7, 8, 11, 12, 13, 14, 15]);
+    }
+
+    #[test]
+    fn lists_cells_of_partial_tiles() {
+        let tiles = Tiles::new(TILE_SIZE + 2, 3);
+        let cells: Vec<_> = tiles.cells_in(&[false, true]).collect();
+
+        let expected: Vec<_> = (0..3).flat_map(|y| (TILE_SIZE..TILE_SIZE + 2).map(move |x| (x, y))).collect();
+        assert_eq!(cells, expected);
+        assert_eq!(tiles.cells_in(&[true, false]).count(), 3 * TILE_SIZE as usize);
+    }
+}
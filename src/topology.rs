@@ -0,0 +1,119 @@
+use wasm_bindgen::prelude::*;
+
+/// How the edges of a [Universe] are joined together.
+#[wasm_bindgen]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Left/right and top/bottom edges wrap around.
+    #[default]
+    Torus = 0,
+
+    /// Cells beyond the edges are always dead.
+    Plane = 1,
+
+    /// Left/right edges wrap around, cells beyond the top and bottom are dead.
+    Cylinder = 2,
+
+    /// Left/right edges wrap around, top/bottom edges wrap with a horizontal flip.
+    KleinBottle = 3,
+
+    /// Both pairs of edges wrap with a flip (the real projective plane).
+    CrossSurface = 4,
+}
+
+impl Topology {
+    /// Maps the (possibly out-of-bounds) point ([x], [y]) onto a
+    /// [width] x [height] surface with this topology. Returns None if the
+    /// point falls off the edge of the surface.
+    pub fn resolve(self, x: i64, y: i64, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (width, height) = (i64::from(width), i64::from(height));
+
+        if (0..width).contains(&x) && (0..height).contains(&y) {
+            return Some((x as u32, y as u32));
+        }
+
+        let (wraps_x, wraps_y, twists_x, twists_y) = self.edges();
+
+        if (!wraps_x && !(0..width).contains(&x)) || (!wraps_y && !(0..height).contains(&y)) {
+            return None;
+        }
+
+        // Crossing the top/bottom edge an odd number of times on a twisted
+        // surface mirrors x (and likewise for the left/right edges and y).
+        let mut x = x;
+        let mut y = y;
+
+        if y.div_euclid(height) % 2 != 0 && twists_y {
+            x = width - 1 - x;
+        }
+        y = y.rem_euclid(height);
+
+        if x.div_euclid(width) % 2 != 0 && twists_x {
+            y = height - 1 - y;
+        }
+        x = x.rem_euclid(width);
+
+        Some((x as u32, y as u32))
+    }
+
+    /// Returns whether the (left/right, top/bottom) edges wrap around.
+    pub fn wraps(self) -> (bool, bool) {
+        let (wraps_x, wraps_y, _, _) = self.edges();
+        (wraps_x, wraps_y)
+    }
+
+    /// Returns whether the left/right and top/bottom edges wrap, followed by
+    /// whether they're twisted (mirrored) as they do.
+    fn edges(self) -> (bool, bool, bool, bool) {
+        match self {
+            Topology::Torus => (true, true, false, false),
+            Topology::Plane => (false, false, false, false),
+            Topology::Cylinder => (true, false, false, false),
+            Topology::KleinBottle => (true, true, false, true),
+            Topology::CrossSurface => (true, true, true, true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Topology;
+
+    #[test]
+    fn resolves_points_inside_unchanged() {
+        for topology in [Topology::Torus, Topology::Plane, Topology::Cylinder, Topology::KleinBottle, Topology::CrossSurface] {
+            assert_eq!(topology.resolve(0, 0, 4, 3), Some((0, 0)));
+            assert_eq!(topology.resolve(3, 2, 4, 3), Some((3, 2)));
+        }
+    }
+
+    #[test]
+    fn resolves_points_across_edges() {
+        assert_eq!(Topology::Torus.resolve(-1, -1, 4, 3), Some((3, 2)));
+        assert_eq!(Topology::Torus.resolve(9, 7, 4, 3), Some((1, 1)));
+
+        assert_eq!(Topology::Plane.resolve(-1, 0, 4, 3), None);
+        assert_eq!(Topology::Plane.resolve(0, 3, 4, 3), None);
+
+        assert_eq!(Topology::Cylinder.resolve(-1, 1, 4, 3), Some((3, 1)));
+        assert_eq!(Topology::Cylinder.resolve(1, -1, 4, 3), None);
+
+        // Only the top/bottom edges of a Klein bottle mirror.
+        assert_eq!(Topology::KleinBottle.resolve(-1, 1, 4, 3), Some((3, 1)));
+        assert_eq!(Topology::KleinBottle.resolve(1, -1, 4, 3), Some((2, 2)));
+        assert_eq!(Topology::KleinBottle.resolve(1, -4, 4, 3), Some((1, 2)));
+
+        assert_eq!(Topology::CrossSurface.resolve(-1, 0, 4, 3), Some((3, 2)));
+        assert_eq!(Topology::CrossSurface.resolve(1, 3, 4, 3), Some((2, 0)));
+    }
+
+    #[test]
+    fn reports_wrapping_edges() {
+        assert_eq!(Topology::Torus.wraps(), (true, true));
+        assert_eq!(Topology::Plane.wraps(), (false, false));
+        assert_eq!(Topology::Cylinder.wraps(), (true, false));
+        assert_eq!(Topology::KleinBottle.wraps(), (true, true));
+        assert_eq!(Topology::CrossSurface.wraps(), (true, true));
+    }
+}